@@ -0,0 +1,115 @@
+//! Load test for the cache client: how many `EXISTS` commands per second concurrent requests
+//! get through, compared with the previous `Arc<Mutex<MultiplexedConnection>>` client.
+//! `EXISTS` is what every authenticated request sends to check the token blacklist.
+//!
+//! Needs a running Redis/KeyDB (`REDIS_URL` in the app config, e.g. `docker compose up keydb`):
+//!
+//! ```text
+//! cargo bench --bench cache_throughput
+//! BENCH_CONCURRENCY=256 BENCH_SECONDS=10 cargo bench --bench cache_throughput
+//! ```
+
+use std::env;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use axum_seaorm_app::config::app_config::AppConfig;
+use axum_seaorm_app::config::cache::Cache;
+use axum_seaorm_app::config::redis::RedisCache;
+use redis::AsyncCommands;
+use redis::aio::MultiplexedConnection;
+use tokio::sync::Mutex;
+
+const KEY: &str = "bench:revoked:jti:missing";
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Runs `concurrency` tasks that send commands in a loop for `duration` and returns the
+/// number of commands per second.
+async fn measure<F, Fut>(concurrency: usize, duration: Duration, command: F) -> f64
+where
+    F: Fn() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let done = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+
+    let tasks: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let command = command.clone();
+            let done = done.clone();
+            tokio::spawn(async move {
+                while started.elapsed() < duration {
+                    command().await;
+                    done.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.expect("benchmark task panicked");
+    }
+
+    done.load(Ordering::Relaxed) as f64 / started.elapsed().as_secs_f64()
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    let config = AppConfig::load().expect("Failed to load the configuration");
+    let redis_url = config
+        .redis_url
+        .expect("REDIS_URL must be set")
+        .expose()
+        .to_owned();
+    let concurrency = env_or("BENCH_CONCURRENCY", 64);
+    let duration = Duration::from_secs(env_or("BENCH_SECONDS", 5));
+
+    // The previous client: one connection behind a lock
+    let client = redis::Client::open(redis_url.as_str()).expect("Invalid Redis URL");
+    let locked: Arc<Mutex<MultiplexedConnection>> = Arc::new(Mutex::new(
+        client
+            .get_multiplexed_tokio_connection()
+            .await
+            .expect("Failed to connect to Redis/KeyDB"),
+    ));
+    let locked_ops = measure(concurrency, duration, move || {
+        let locked = locked.clone();
+        async move {
+            let mut conn = locked.lock().await;
+            let _: bool = conn.exists(KEY).await.expect("EXISTS failed");
+        }
+    })
+    .await;
+
+    // The current client: cloned handles on a shared multiplexed connection
+    let cache = RedisCache::connect(&redis_url)
+        .await
+        .expect("Failed to connect to Redis/KeyDB");
+    let shared_ops = measure(concurrency, duration, move || {
+        let cache = cache.clone();
+        async move {
+            cache.exists(KEY).await.expect("EXISTS failed");
+        }
+    })
+    .await;
+
+    println!(
+        "{} concurrent tasks, {}s per client",
+        concurrency,
+        duration.as_secs()
+    );
+    println!("mutex-wrapped connection: {:>10.0} ops/s", locked_ops);
+    println!("RedisCache:               {:>10.0} ops/s", shared_ops);
+    println!(
+        "speedup:                  {:>10.2}x",
+        shared_ops / locked_ops
+    );
+}
@@ -1,9 +1,29 @@
 pub mod m20250909_145739_create_users_table;
+pub mod m20261018_100000_create_refresh_tokens_table;
+pub mod m20261018_110000_create_sessions_table;
+pub mod m20261018_120000_add_token_version_to_user;
+pub mod m20261018_130000_add_two_factor_auth;
+pub mod m20261018_140000_create_categories_table;
+pub mod m20261018_150000_create_products_tables;
+pub mod m20261018_160000_create_cart_and_orders_tables;
+pub mod m20261018_170000_create_order_status_history_table;
+pub mod m20261018_180000_create_payments_tables;
 pub use sea_orm_migration::prelude::*;
 pub struct Migrator;
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20250909_145739_create_users_table::Migration)]
+        vec![
+            Box::new(m20250909_145739_create_users_table::Migration),
+            Box::new(m20261018_100000_create_refresh_tokens_table::Migration),
+            Box::new(m20261018_110000_create_sessions_table::Migration),
+            Box::new(m20261018_120000_add_token_version_to_user::Migration),
+            Box::new(m20261018_130000_add_two_factor_auth::Migration),
+            Box::new(m20261018_140000_create_categories_table::Migration),
+            Box::new(m20261018_150000_create_products_tables::Migration),
+            Box::new(m20261018_160000_create_cart_and_orders_tables::Migration),
+            Box::new(m20261018_170000_create_order_status_history_table::Migration),
+            Box::new(m20261018_180000_create_payments_tables::Migration),
+        ]
     }
 }
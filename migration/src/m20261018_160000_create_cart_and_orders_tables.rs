@@ -0,0 +1,237 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CartItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CartItems::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CartItems::UserId).integer().not_null())
+                    .col(ColumnDef::new(CartItems::VariantId).integer().not_null())
+                    .col(ColumnDef::new(CartItems::Quantity).integer().not_null())
+                    .col(
+                        ColumnDef::new(CartItems::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(CartItems::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_cart_items_user_id")
+                            .from(CartItems::Table, CartItems::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_cart_items_variant_id")
+                            .from(CartItems::Table, CartItems::VariantId)
+                            .to(ProductVariants::Table, ProductVariants::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A variant appears at most once in a user's cart
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_cart_items_user_id_variant_id")
+                    .table(CartItems::Table)
+                    .col(CartItems::UserId)
+                    .col(CartItems::VariantId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Orders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Orders::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Orders::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Orders::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    // Total in minor units (e.g. cents)
+                    .col(ColumnDef::new(Orders::Total).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Orders::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Orders::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_orders_user_id")
+                            .from(Orders::Table, Orders::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_orders_user_id")
+                    .table(Orders::Table)
+                    .col(Orders::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderItems::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderItems::OrderId).integer().not_null())
+                    // Kept when the variant is deleted; the columns below are a snapshot
+                    .col(ColumnDef::new(OrderItems::VariantId).integer().null())
+                    .col(ColumnDef::new(OrderItems::Sku).string().not_null())
+                    .col(ColumnDef::new(OrderItems::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderItems::UnitPrice)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderItems::Quantity).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderItems::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_items_order_id")
+                            .from(OrderItems::Table, OrderItems::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_items_variant_id")
+                            .from(OrderItems::Table, OrderItems::VariantId)
+                            .to(ProductVariants::Table, ProductVariants::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_order_items_order_id")
+                    .table(OrderItems::Table)
+                    .col(OrderItems::OrderId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderItems::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Orders::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CartItems::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CartItems {
+    Table,
+    Id,
+    UserId,
+    VariantId,
+    Quantity,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    UserId,
+    Status,
+    Total,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OrderItems {
+    Table,
+    Id,
+    OrderId,
+    VariantId,
+    Sku,
+    Name,
+    UnitPrice,
+    Quantity,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ProductVariants {
+    Table,
+    Id,
+}
@@ -0,0 +1,130 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderStatusHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::OrderId)
+                            .integer()
+                            .not_null(),
+                    )
+                    // Null for the entry recording the order being placed
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::FromStatus)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::ToStatus)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderStatusHistory::Note).text().null())
+                    // Null when the change was not made by a user (e.g. a payment webhook)
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::ChangedBy)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_status_history_order_id")
+                            .from(OrderStatusHistory::Table, OrderStatusHistory::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_status_history_changed_by")
+                            .from(OrderStatusHistory::Table, OrderStatusHistory::ChangedBy)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_order_status_history_order_id")
+                    .table(OrderStatusHistory::Table)
+                    .col(OrderStatusHistory::OrderId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Supports the status and date filters of the admin order list
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_orders_status_created_at")
+                    .table(Orders::Table)
+                    .col(Orders::Status)
+                    .col(Orders::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_orders_status_created_at")
+                    .table(Orders::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(OrderStatusHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderStatusHistory {
+    Table,
+    Id,
+    OrderId,
+    FromStatus,
+    ToStatus,
+    Note,
+    ChangedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    Status,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
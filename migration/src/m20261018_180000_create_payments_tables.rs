@@ -0,0 +1,154 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Payments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Payments::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Payments::OrderId).integer().not_null())
+                    .col(ColumnDef::new(Payments::Provider).string().not_null())
+                    // Id of the payment intent at the provider
+                    .col(ColumnDef::new(Payments::IntentId).string().not_null())
+                    // Amount in minor units (e.g. cents)
+                    .col(ColumnDef::new(Payments::Amount).big_integer().not_null())
+                    .col(ColumnDef::new(Payments::Currency).string().not_null())
+                    .col(ColumnDef::new(Payments::Status).string().not_null())
+                    .col(
+                        ColumnDef::new(Payments::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Payments::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payments_order_id")
+                            .from(Payments::Table, Payments::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payments_provider_intent_id")
+                    .table(Payments::Table)
+                    .col(Payments::Provider)
+                    .col(Payments::IntentId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payments_order_id")
+                    .table(Payments::Table)
+                    .col(Payments::OrderId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PaymentEvents::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PaymentEvents::Provider).string().not_null())
+                    // Id of the webhook event at the provider
+                    .col(ColumnDef::new(PaymentEvents::EventId).string().not_null())
+                    .col(ColumnDef::new(PaymentEvents::EventType).string().not_null())
+                    .col(ColumnDef::new(PaymentEvents::IntentId).string().not_null())
+                    .col(
+                        ColumnDef::new(PaymentEvents::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A webhook event is applied at most once, however often it is delivered
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_events_provider_event_id")
+                    .table(PaymentEvents::Table)
+                    .col(PaymentEvents::Provider)
+                    .col(PaymentEvents::EventId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentEvents::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Payments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Payments {
+    Table,
+    Id,
+    OrderId,
+    Provider,
+    IntentId,
+    Amount,
+    Currency,
+    Status,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PaymentEvents {
+    Table,
+    Id,
+    Provider,
+    EventId,
+    EventType,
+    IntentId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
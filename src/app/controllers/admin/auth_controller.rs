@@ -1,30 +1,51 @@
 use anyhow::Result;
 use axum::{
     Json,
-    extract::{Extension, Path},
-    http::StatusCode,
+    extract::{Path, State},
 };
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
-use crate::config::blacklist::blacklist_token;
+use crate::config::app_state::AppState;
+use crate::config::blacklist::{blacklist_token, is_blacklisted, is_token_revoked};
+use crate::config::client_info::ClientInfo;
 use crate::config::jwt::{
-    create_email_verification_jwt, create_jwt, verify_email_verification_jwt, verify_jwt,
+    TokenType, create_email_verification_jwt, create_jwt, create_mfa_pending_jwt,
+    create_password_reset_jwt, verify_email_verification_jwt, verify_jwt, verify_mfa_pending_jwt,
+    verify_password_reset_jwt,
 };
 // use crate::config::mail::EmailSender;
+use crate::config::login_attempts::{
+    LOCKOUT_DURATION, LoginThrottle, check_login_throttle, clear_failed_logins, record_failed_login,
+};
 use crate::config::rabbitmq::EmailJob;
-use crate::config::rabbitmq::publish_to_queue; // Import publish_to_queue
-use crate::models::{user, user::Entity as User};
+use crate::config::refresh_tokens::{RefreshTokenError, rotate_refresh_token, store_refresh_token};
+use crate::config::sessions::{create_session, revoke_all_sessions, revoke_session, touch_session};
+use crate::config::two_factor::verify_second_factor;
+use crate::config::validation::{ValidatedJson, validate_password_strength};
+use crate::errors::AppError;
+use crate::models::{refresh_token, user, user::Entity as User};
 
 use bcrypt::verify;
 use chrono::Utc;
-use tracing::{error, info};
+use std::sync::LazyLock;
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
 /// A struct to represent the user registration request body.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct RegisterRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Must be between 1 and 255 characters long."
+    ))]
     pub name: String,
+    #[validate(email(message = "Must be a valid email address."))]
     pub email: String,
+    #[validate(custom = "validate_password_strength")]
     pub password: String,
 }
 
@@ -37,13 +58,6 @@ pub struct AuthResponse {
     pub token_type: String,
 }
 
-/// A struct to represent a custom error response.
-#[derive(Debug, Serialize)]
-pub struct AuthErrorResponse {
-    pub status: bool,
-    pub message: String,
-}
-
 /// A struct to represent a successful verification response.
 #[derive(Debug, Serialize)]
 pub struct VerifyResponse {
@@ -51,44 +65,96 @@ pub struct VerifyResponse {
     pub message: String,
 }
 
+/// Creates an access and refresh token pair and records the refresh token in the store.
+/// Passing the rotated `parent` keeps the new refresh token in the parent's family (session);
+/// otherwise a new session is started.
+/// The tokens carry the user's current role and token version, and `mfa` records whether
+/// the login was confirmed with a second factor.
+pub async fn issue_auth_tokens(
+    state: &AppState,
+    user_model: &user::Model,
+    parent: Option<&refresh_token::Model>,
+    mfa: bool,
+    client: &ClientInfo,
+) -> Result<AuthResponse, AppError> {
+    let (family_id, parent_id) = match parent {
+        Some(parent) => (parent.family_id.clone(), Some(parent.id)),
+        None => (Uuid::new_v4().to_string(), None),
+    };
+
+    let db = &state.db;
+    let session_result = match parent {
+        Some(_) => touch_session(db, &family_id, client).await,
+        None => create_session(db, &family_id, user_model.id, client)
+            .await
+            .map(|_| ()),
+    };
+    session_result?;
+
+    let access_token = create_jwt(
+        &state.jwt_keys,
+        &user_model.id.to_string(),
+        &user_model.role,
+        &family_id,
+        user_model.token_version,
+        mfa,
+        TokenType::Access,
+        state.config.access_token_ttl,
+    )
+    .map_err(|e| AppError::Internal(e.into()))?;
+
+    let refresh_token = create_jwt(
+        &state.jwt_keys,
+        &user_model.id.to_string(),
+        &user_model.role,
+        &family_id,
+        user_model.token_version,
+        mfa,
+        TokenType::Refresh,
+        state.config.refresh_token_ttl,
+    )
+    .map_err(|e| AppError::Internal(e.into()))?;
+
+    store_refresh_token(
+        db,
+        user_model.id,
+        &refresh_token,
+        &family_id,
+        parent_id,
+        client.user_agent.clone(),
+        state.config.refresh_token_ttl as i64,
+    )
+    .await?;
+
+    Ok(AuthResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_owned(),
+    })
+}
+
 /// Handles the admin registration logic.
 pub async fn register(
-    Extension(db): Extension<DatabaseConnection>,
-    Json(payload): Json<RegisterRequest>,
-) -> Result<Json<AuthResponse>, (StatusCode, Json<AuthErrorResponse>)> {
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
     // Check if user with this email already exists
     let existing_user = User::find()
         .filter(user::Column::Email.eq(payload.email.clone()))
-        .one(&db)
-        .await
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Database error: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
+        .one(&state.db)
+        .await?;
 
     if existing_user.is_some() {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Email already exists.".to_string(),
-        };
         error!(
             "Registration failed: Email already exists for user: {}",
             payload.email
         );
-        return Err((StatusCode::CONFLICT, Json(error_response)));
+        return Err(AppError::Conflict("Email already exists.".to_owned()));
     }
 
     // Hash the password
-    let hashed_password = bcrypt::hash(payload.password, bcrypt::DEFAULT_COST).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Password hashing error: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    let hashed_password = bcrypt::hash(payload.password, bcrypt::DEFAULT_COST)?;
 
     // Create a new user active model with role "Admin"
     let new_user = user::ActiveModel {
@@ -101,51 +167,42 @@ pub async fn register(
     };
 
     // Save the user to the database
-    let user = new_user.save(&db).await.map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Database issue: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
-
-    // Get the user ID
-    let user_id = user.id.unwrap().to_string();
+    let user_model = new_user.insert(&state.db).await?;
 
     // Create JWT tokens (access and refresh)
-    let access_token = create_jwt(&user_id, &"Admin".to_owned(), 3600).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Access token creation failed: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    let tokens = issue_auth_tokens(&state, &user_model, None, false, &client).await?;
 
-    let refresh_token = create_jwt(&user_id, &"Admin".to_owned(), 2592000).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Refresh token creation failed: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    // Send the verification email through RabbitMQ
+    queue_verification_email(&state, &user_model.id.to_string(), &payload.email, "admin").await?;
+    info!("Verification email send to: {}", payload.email);
+    // Return the authentication tokens
+    Ok(Json(tokens))
+}
 
+/// Creates a verification token for the user and queues the verification email.
+/// `route_prefix` selects the route tree (`admin` or `customer`) the link points to.
+pub async fn queue_verification_email(
+    state: &AppState,
+    user_id: &str,
+    email: &str,
+    route_prefix: &str,
+) -> Result<(), AppError> {
     // Create a verification token and send it to RabbitMQ
-    let verification_token = create_email_verification_jwt(&user_id, 86400).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Verification token creation failed: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    let verification_token = create_email_verification_jwt(
+        &state.jwt_keys,
+        user_id,
+        state.config.email_verification_ttl,
+    )
+    .map_err(|e| AppError::Internal(e.into()))?;
 
     let verification_link = format!(
-        "http://localhost:8080/admin/verify-email/{}",
-        verification_token
+        "{}/{}/verify-email/{}",
+        state.config.app_url, route_prefix, verification_token
     );
 
     // Create the email task (email job) to be processed in the background
     let email_task = EmailJob {
-        to: payload.email.clone(),
+        to: email.to_owned(),
         subject: "Verify your email address".to_string(),
         body: format!(
             "<html><body><h1>ইমেল যাচাই করুন</h1><p>আপনার অ্যাকাউন্ট যাচাই করতে নিচের লিংকে ক্লিক করুন:</p><a href=\"{}\">ইমেল যাচাই করুন</a></body></html>",
@@ -154,102 +211,363 @@ pub async fn register(
     };
 
     // Publish the email task to the RabbitMQ queue
-    publish_to_queue(&email_task, "email_queue")
-        .await
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Failed to send email task to RabbitMQ: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
-    info!("Verification email send to: {}", payload.email);
-    // Return the authentication tokens
-    Ok(Json(AuthResponse {
-        access_token,
-        refresh_token,
-        token_type: "Bearer".to_owned(),
+    state.queue.publish_email(&email_task).await?;
+    Ok(())
+}
+
+/// Maximum number of verification emails that can be resent per address in one window.
+const MAX_VERIFICATION_RESENDS: i64 = 3;
+/// Length of the resend rate-limit window (seconds).
+const VERIFICATION_RESEND_WINDOW: usize = 3600;
+
+/// A struct to represent the resend verification email request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResendVerificationRequest {
+    #[validate(email(message = "Must be a valid email address."))]
+    pub email: String,
+}
+
+/// Re-sends the verification email to an unverified user with the given role.
+/// The same response is returned whether or not the account exists, so the endpoint
+/// can't be used to probe for registered emails.
+pub async fn resend_verification(
+    state: &AppState,
+    email: &str,
+    role: &str,
+    route_prefix: &str,
+) -> Result<Json<VerifyResponse>, AppError> {
+    // Cap the number of resends per email address
+    let rate_limit_key = format!("verify_email_resend:{}", email.to_lowercase());
+    let attempts = state
+        .cache
+        .incr(&rate_limit_key, VERIFICATION_RESEND_WINDOW)
+        .await?;
+
+    if attempts > MAX_VERIFICATION_RESENDS {
+        return Err(AppError::TooManyRequests {
+            message: "Too many verification emails requested. Please try again later.".to_owned(),
+            retry_after: None,
+        });
+    }
+
+    let user_model = User::find()
+        .filter(user::Column::Email.eq(email))
+        .filter(user::Column::Role.eq(role))
+        .one(&state.db)
+        .await?;
+
+    if let Some(user_model) = user_model
+        && user_model.email_verified_at.is_none()
+    {
+        queue_verification_email(
+            state,
+            &user_model.id.to_string(),
+            &user_model.email,
+            route_prefix,
+        )
+        .await?;
+        info!("Verification email resent to: {}", user_model.email);
+    }
+
+    Ok(Json(VerifyResponse {
+        status: true,
+        message:
+            "If the account exists and is not verified, a new verification email has been sent."
+                .to_owned(),
     }))
 }
+
+/// Handles resending the admin verification email.
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ResendVerificationRequest>,
+) -> Result<Json<VerifyResponse>, AppError> {
+    resend_verification(&state, &payload.email, "Admin", "admin").await
+}
+
 /// A struct to represent the user login request body.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
+    #[validate(email(message = "Must be a valid email address."))]
     pub email: String,
+    #[validate(length(min = 1, message = "Must not be empty."))]
     pub password: String,
 }
 
+/// Rejects a login attempt while the email is in backoff or the account or IP address is locked.
+pub async fn ensure_login_allowed(
+    state: &AppState,
+    email: &str,
+    client: &ClientInfo,
+) -> Result<(), AppError> {
+    let throttle = check_login_throttle(&state.cache, email, client.ip_address.as_deref()).await?;
+
+    match throttle {
+        LoginThrottle::Allowed => Ok(()),
+        LoginThrottle::Backoff(seconds) => Err(AppError::TooManyRequests {
+            message: format!(
+                "Too many failed login attempts. Please wait {} seconds before trying again.",
+                seconds
+            ),
+            retry_after: Some(seconds.max(1) as u64),
+        }),
+        LoginThrottle::Locked(seconds) => Err(AppError::Locked(format!(
+            "Account temporarily locked after too many failed login attempts. Try again in {} minutes or reset your password.",
+            (seconds + 59) / 60
+        ))),
+    }
+}
+
+/// Hash of a throwaway password, checked when a login names an unknown email.
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    bcrypt::hash("dummy-password", bcrypt::DEFAULT_COST).expect("Failed to hash dummy password")
+});
+
+/// Runs a password check for a login with an unknown email, so it takes as long as one with
+/// a wrong password and the response time doesn't reveal which emails have an account.
+pub fn verify_dummy_password(password: &str) {
+    let _ = verify(password, &DUMMY_PASSWORD_HASH);
+}
+
+/// Records a failed login and returns the error response for it.
+/// When the failure locks the account, its owner is warned by email.
+pub async fn failed_login(
+    state: &AppState,
+    user_model: Option<&user::Model>,
+    email: &str,
+    client: &ClientInfo,
+) -> AppError {
+    let locked = match record_failed_login(&state.cache, email, client.ip_address.as_deref()).await
+    {
+        Ok(locked) => locked,
+        Err(e) => {
+            error!("Failed to record failed login for {}: {}", email, e);
+            false
+        }
+    };
+
+    if !locked {
+        return AppError::Unauthorized("Wrong email or password.".to_owned());
+    }
+
+    warn!(
+        target: "security",
+        email,
+        ip_address = ?client.ip_address,
+        "Account locked after too many failed login attempts"
+    );
+
+    if let Some(user_model) = user_model {
+        let email_task = EmailJob {
+            to: user_model.email.clone(),
+            subject: "Suspicious login attempts on your account".to_string(),
+            body: format!(
+                "<html><body><h1>Suspicious login attempts</h1><p>We noticed several failed attempts to log in to your account (last attempt from IP address {}). Your account has been locked for {} minutes.</p><p>If this wasn't you, we recommend resetting your password.</p></body></html>",
+                client.ip_address.as_deref().unwrap_or("unknown"),
+                LOCKOUT_DURATION / 60
+            ),
+        };
+        if let Err(e) = state.queue.publish_email(&email_task).await {
+            error!("Failed to queue suspicious login email: {}", e);
+        }
+    }
+
+    AppError::Locked(format!(
+        "Account temporarily locked after too many failed login attempts. Try again in {} minutes or reset your password.",
+        LOCKOUT_DURATION / 60
+    ))
+}
+
+/// Lifetime of an "mfa pending" token (seconds).
+const MFA_PENDING_TOKEN_TTL: usize = 300;
+/// Maximum number of second factor attempts per "mfa pending" token.
+const MAX_MFA_ATTEMPTS: i64 = 5;
+
+/// A struct to represent a login that still needs a second factor.
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub status: bool,
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub message: String,
+}
+
+/// The admin login response: either the tokens, or a challenge for the second factor.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Tokens(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
 /// Handles the admin login logic.
+/// Users with two-factor authentication enabled get an "mfa pending" token instead of the
+/// login tokens; it is exchanged at `/login/2fa` together with a valid code.
 pub async fn login(
-    Extension(db): Extension<DatabaseConnection>,
-    Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, (StatusCode, Json<AuthErrorResponse>)> {
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    ensure_login_allowed(&state, &payload.email, &client).await?;
+
     // Find the user by email
     let user_model = User::find()
-        .filter(user::Column::Email.eq(payload.email))
-        .one(&db)
-        .await
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Database error: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
+        .filter(user::Column::Email.eq(&payload.email))
+        .one(&state.db)
+        .await?;
 
     if let Some(user_model) = user_model {
         // Verify the password
-        let password_is_valid = verify(payload.password, &user_model.password).map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Password verification error: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
+        let password_is_valid = verify(payload.password, &user_model.password)?;
 
         if !password_is_valid {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: "Wrong email or password.".to_string(),
-            };
-            return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+            return Err(failed_login(&state, Some(&user_model), &payload.email, &client).await);
+        }
+
+        // A failure to reset the counters must not block a valid login
+        if let Err(e) = clear_failed_logins(&state.cache, &payload.email).await {
+            error!("Failed to clear failed logins for {}: {}", payload.email, e);
+        }
+
+        // Ask for the second factor before handing out any login token.
+        if user_model.totp_enabled_at.is_some() {
+            let mfa_token = create_mfa_pending_jwt(
+                &state.jwt_keys,
+                &user_model.id.to_string(),
+                user_model.token_version,
+                MFA_PENDING_TOKEN_TTL,
+            )
+            .map_err(|e| AppError::Internal(e.into()))?;
+
+            return Ok(Json(LoginResponse::MfaRequired(MfaChallengeResponse {
+                status: true,
+                mfa_required: true,
+                mfa_token,
+                message: "Enter the code from your authenticator app or a recovery code."
+                    .to_owned(),
+            })));
         }
 
         // Create a new access token and a new refresh token.
-        let access_token =
-            create_jwt(&user_model.id.to_string(), &user_model.role, 3600).map_err(|e| {
-                let error_response = AuthErrorResponse {
-                    status: false,
-                    message: format!("Access token not created: {}", e),
-                };
-                (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-            })?;
-
-        let refresh_token = create_jwt(&user_model.id.to_string(), &user_model.role, 2592000)
-            .map_err(|e| {
-                let error_response = AuthErrorResponse {
-                    status: false,
-                    message: format!("Refresh token not created: {}", e),
-                };
-                (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-            })?;
-
-        Ok(Json(AuthResponse {
-            access_token,
-            refresh_token,
-            token_type: "Bearer".to_owned(),
-        }))
+        let tokens = issue_auth_tokens(&state, &user_model, None, false, &client).await?;
+
+        Ok(Json(LoginResponse::Tokens(tokens)))
     } else {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Wrong email or password.".to_string(),
-        };
-        Err((StatusCode::UNAUTHORIZED, Json(error_response)))
+        verify_dummy_password(&payload.password);
+        Err(failed_login(&state, None, &payload.email, &client).await)
     }
 }
 
+/// A struct to represent the second step of a two-factor login.
+#[derive(Debug, Deserialize, Validate)]
+pub struct TwoFactorLoginRequest {
+    #[validate(length(min = 1, message = "Must not be empty."))]
+    pub mfa_token: String,
+    #[validate(length(
+        min = 6,
+        max = 32,
+        message = "Must be between 6 and 32 characters long."
+    ))]
+    pub code: String,
+}
+
+/// Handles the second step of the admin login.
+/// Exchanges an "mfa pending" token and a TOTP or recovery code for the login tokens.
+pub async fn verify_two_factor_login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<TwoFactorLoginRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    let invalid_token =
+        || AppError::Unauthorized("Invalid or expired MFA token. Please log in again.".to_owned());
+
+    let token_data =
+        verify_mfa_pending_jwt(&state.jwt_keys, &payload.mfa_token).map_err(|_| invalid_token())?;
+
+    // A pending token is single use
+    let is_used = is_blacklisted(&state.cache, &token_data.claims.jti).await?;
+    if is_used {
+        return Err(invalid_token());
+    }
+
+    // Cap the number of codes that can be tried with one pending token
+    let attempts = state
+        .cache
+        .incr(
+            &format!("mfa_attempts:{}", token_data.claims.jti),
+            MFA_PENDING_TOKEN_TTL,
+        )
+        .await?;
+    if attempts > MAX_MFA_ATTEMPTS {
+        return Err(AppError::TooManyRequests {
+            message: "Too many invalid codes. Please log in again.".to_owned(),
+            retry_after: None,
+        });
+    }
+
+    let user_id: i32 = token_data.claims.id.parse().map_err(|_| invalid_token())?;
+
+    let user_model = User::find_by_id(user_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(invalid_token)?;
+
+    // The token dies with a password reset or "log out everywhere"
+    if token_data.claims.ver != user_model.token_version {
+        return Err(invalid_token());
+    }
+
+    let Some(encrypted_secret) = user_model
+        .totp_secret
+        .as_deref()
+        .filter(|_| user_model.totp_enabled_at.is_some())
+    else {
+        return Err(invalid_token());
+    };
+
+    // Claim the token before checking the code, so two requests can't both use it.
+    // A wrong code gives the claim back for the remaining attempts.
+    let claim_key = format!("mfa_claim:{}", token_data.claims.jti);
+    let ttl = token_data.claims.exp as i64 - Utc::now().timestamp();
+    if ttl <= 0
+        || !state
+            .cache
+            .set_if_absent(&claim_key, "1", ttl as usize)
+            .await?
+    {
+        return Err(invalid_token());
+    }
+
+    let code_is_valid = verify_second_factor(
+        &state.db,
+        &state.cache,
+        &state.config,
+        user_id,
+        encrypted_secret,
+        &payload.code,
+    )
+    .await;
+    if !matches!(code_is_valid, Ok(true)) {
+        state.cache.delete(&claim_key).await?;
+    }
+
+    if !code_is_valid? {
+        return Err(AppError::Unauthorized(
+            "Invalid authentication code.".to_owned(),
+        ));
+    }
+
+    blacklist_token(&state.cache, &token_data.claims.jti, token_data.claims.exp).await?;
+
+    let tokens = issue_auth_tokens(&state, &user_model, None, true, &client).await?;
+
+    Ok(Json(tokens))
+}
+
 /// A struct to represent the request to logout.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct LogoutRequest {
+    #[validate(length(min = 1, message = "Must not be empty."))]
     pub token: String,
 }
 
@@ -260,30 +578,29 @@ pub struct LogoutResponse {
     pub message: String,
 }
 
-/// Handles the user logout logic by blacklisting the token.
+/// Handles the user logout logic by blacklisting the token and ending its session.
 /// This is the best practice for revoking JWTs before they expire.
 pub async fn logout(
-    Json(payload): Json<LogoutRequest>,
-) -> Result<Json<LogoutResponse>, (StatusCode, Json<AuthErrorResponse>)> {
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<LogoutRequest>,
+) -> Result<Json<LogoutResponse>, AppError> {
     // Decode the token to get its claims and expiration time
-    let token_data = verify_jwt(&payload.token).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Invalid Token: {}", e),
-        };
-        (StatusCode::UNAUTHORIZED, Json(error_response))
-    })?;
-
-    // Blacklist the token in Redis
-    blacklist_token(&payload.token, token_data.claims.exp)
-        .await
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Token not blacklisted: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
+    // Either an access or a refresh token can be revoked
+    let token_data = verify_jwt(&state.jwt_keys, &payload.token, TokenType::Access)
+        .or_else(|_| verify_jwt(&state.jwt_keys, &payload.token, TokenType::Refresh))
+        .map_err(|_| AppError::Unauthorized("Invalid or expired token.".to_owned()))?;
+
+    // Revoke the token itself by its jti
+    blacklist_token(&state.cache, &token_data.claims.jti, token_data.claims.exp).await?;
+
+    // End the session, so its other access and refresh tokens stop working too
+    revoke_session(
+        &state.db,
+        &state.cache,
+        &token_data.claims.sid,
+        state.config.access_token_ttl,
+    )
+    .await?;
 
     Ok(Json(LogoutResponse {
         status: true,
@@ -292,143 +609,105 @@ pub async fn logout(
 }
 
 /// A struct to represent the refresh token request body.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct RefreshTokenRequest {
+    #[validate(length(min = 1, message = "Must not be empty."))]
     pub refresh_token: String,
 }
 
 /// Handles the token refresh logic.
+/// Every refresh token can be used once; it is rotated into a new token of the same family.
 pub async fn refresh_token(
-    Json(payload): Json<RefreshTokenRequest>,
-) -> Result<Json<AuthResponse>, (StatusCode, Json<AuthErrorResponse>)> {
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<RefreshTokenRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
     // 1. Verify the refresh token's validity.
-    let token_data = verify_jwt(&payload.refresh_token).map_err(|_| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Invalid or expired refresh token.".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(error_response))
-    })?;
+    let token_data = verify_jwt(&state.jwt_keys, &payload.refresh_token, TokenType::Refresh)
+        .map_err(|_| AppError::Unauthorized("Invalid or expired refresh token.".to_owned()))?;
 
-    // 2. Check if the token is blacklisted.
-    let is_blacklisted = crate::config::redis::key_exists(&payload.refresh_token)
-        .await
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Failed to check token blacklist: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
-
-    if is_blacklisted {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Refresh token is blacklisted.".to_string(),
-        };
-        return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+    // 2. Reject refresh tokens that were logged out, whose session was revoked
+    // or whose token version is outdated.
+    let is_revoked = is_token_revoked(&state.db, &state.cache, &token_data.claims).await?;
+
+    if is_revoked {
+        return Err(AppError::Unauthorized(
+            "Refresh token has been revoked.".to_owned(),
+        ));
     }
 
-    // 3. Blacklist the old refresh token to prevent reuse.
-    blacklist_token(&payload.refresh_token, token_data.claims.exp)
-        .await
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Failed to blacklist old refresh token: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
-
-    // 4. Generate a new access token (1 hour) and a new refresh token (30 days).
-    let new_access_token = create_jwt(&token_data.claims.id, &token_data.claims.role, 3600)
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Failed to create new access token: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
-
-    let new_refresh_token = create_jwt(&token_data.claims.id, &token_data.claims.role, 2592000)
-        .map_err(|e| {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: format!("Failed to create new refresh token: {}", e),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
-
-    // 5. Return the new tokens.
-    Ok(Json(AuthResponse {
-        access_token: new_access_token,
-        refresh_token: new_refresh_token,
-        token_type: "Bearer".to_owned(),
-    }))
+    // 3. Rotate the refresh token. Replaying an already rotated token revokes its family.
+    let parent = rotate_refresh_token(
+        &state.db,
+        &state.cache,
+        &payload.refresh_token,
+        state.config.access_token_ttl,
+    )
+    .await
+    .map_err(|e| match e {
+        RefreshTokenError::Database(e) => AppError::Database(e),
+        RefreshTokenError::Session(e) => AppError::Internal(e),
+        e => AppError::Unauthorized(e.to_string()),
+    })?;
+
+    // 4. Load the user, so the new tokens carry the current role and token version.
+    let user_model = User::find_by_id(parent.user_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found.".to_owned()))?;
+
+    // 5. Generate a new access token and a new refresh token in the same family.
+    // The second factor flag carries over from the rotated token.
+    let tokens = issue_auth_tokens(
+        &state,
+        &user_model,
+        Some(&parent),
+        token_data.claims.mfa,
+        &client,
+    )
+    .await?;
+
+    // 6. Return the new tokens.
+    Ok(Json(tokens))
 }
 
 /// Handles the email verification logic.
 pub async fn verify_email(
-    Extension(db): Extension<DatabaseConnection>,
+    State(state): State<AppState>,
     Path(token): Path<String>,
-) -> Result<Json<VerifyResponse>, (StatusCode, Json<AuthErrorResponse>)> {
+) -> Result<Json<VerifyResponse>, AppError> {
     //  Verify token and get claims
-    let token_data = verify_email_verification_jwt(&token).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Invalid or expired verification token: {}", e),
-        };
-        (StatusCode::BAD_REQUEST, Json(error_response))
-    })?;
+    let token_data = verify_email_verification_jwt(&state.jwt_keys, &token)
+        .map_err(|_| AppError::BadRequest("Invalid or expired verification token.".to_owned()))?;
 
     //  Convert string user_id to i32 (DB id)
-    let user_id: i32 = token_data.claims.id.parse().map_err(|_| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Invalid user ID in token.".to_string(),
-        };
-        (StatusCode::BAD_REQUEST, Json(error_response))
-    })?;
+    let user_id: i32 = token_data
+        .claims
+        .id
+        .parse()
+        .map_err(|_| AppError::BadRequest("Invalid user ID in token.".to_owned()))?;
 
     //  Fetch user from DB
-    let user_model = User::find_by_id(user_id).one(&db).await.map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Database error: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    let user_model = User::find_by_id(user_id).one(&state.db).await?;
 
     let user_model = match user_model {
         Some(u) => u,
         None => {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: "User not found.".to_string(),
-            };
-            return Err((StatusCode::NOT_FOUND, Json(error_response)));
+            return Err(AppError::NotFound("User not found.".to_owned()));
         }
     };
 
     //  Check if already verified
     if user_model.email_verified_at.is_some() {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Email is already verified.".to_string(),
-        };
-        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+        return Err(AppError::BadRequest(
+            "Email is already verified.".to_owned(),
+        ));
     }
 
     //  Update email_verified_at
     let mut user_active_model: user::ActiveModel = user_model.into();
     user_active_model.email_verified_at = Set(Some(Utc::now().naive_utc()));
-    user_active_model.save(&db).await.map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Failed to update email verification status: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    user_active_model.save(&state.db).await?;
 
     //  Success response
     Ok(Json(VerifyResponse {
@@ -436,3 +715,146 @@ pub async fn verify_email(
         message: "Email successfully verified.".to_owned(),
     }))
 }
+
+/// Maximum number of password reset emails that can be requested per address in one window.
+const MAX_PASSWORD_RESET_REQUESTS: i64 = 3;
+/// Length of the password reset rate-limit window (seconds).
+const PASSWORD_RESET_WINDOW: usize = 3600;
+
+/// A struct to represent the forgot password request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Must be a valid email address."))]
+    pub email: String,
+}
+
+/// A struct to represent a password reset response.
+#[derive(Debug, Serialize)]
+pub struct PasswordResetResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Queues a password reset email for the user with the given email and role.
+/// Like `resend_verification`, the response does not reveal whether the account exists.
+pub async fn send_password_reset(
+    state: &AppState,
+    email: &str,
+    role: &str,
+) -> Result<Json<PasswordResetResponse>, AppError> {
+    // Cap the number of reset emails per email address
+    let rate_limit_key = format!("password_reset:{}", email.to_lowercase());
+    let attempts = state
+        .cache
+        .incr(&rate_limit_key, PASSWORD_RESET_WINDOW)
+        .await?;
+
+    if attempts > MAX_PASSWORD_RESET_REQUESTS {
+        return Err(AppError::TooManyRequests {
+            message: "Too many password reset requests. Please try again later.".to_owned(),
+            retry_after: None,
+        });
+    }
+
+    let user_model = User::find()
+        .filter(user::Column::Email.eq(email))
+        .filter(user::Column::Role.eq(role))
+        .one(&state.db)
+        .await?;
+
+    if let Some(user_model) = user_model {
+        // The reset link is valid for `PASSWORD_RESET_TTL` (one hour by default)
+        let reset_token = create_password_reset_jwt(
+            &state.jwt_keys,
+            &user_model.id.to_string(),
+            user_model.token_version,
+            state.config.password_reset_ttl,
+        )
+        .map_err(|e| AppError::Internal(e.into()))?;
+
+        // The frontend page posts the token along with the new password to `reset-password`
+        let reset_link = format!("{}?token={}", state.config.password_reset_url, reset_token);
+
+        let email_task = EmailJob {
+            to: user_model.email.clone(),
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "<html><body><h1>Reset your password</h1><p>Use the link below to choose a new password. It expires in {} minutes.</p><a href=\"{}\">Reset password</a><p>If you did not request a password reset, you can ignore this email.</p></body></html>",
+                state.config.password_reset_ttl.div_ceil(60),
+                reset_link
+            ),
+        };
+
+        state.queue.publish_email(&email_task).await?;
+        info!("Password reset email send to: {}", user_model.email);
+    }
+
+    Ok(Json(PasswordResetResponse {
+        status: true,
+        message: "If the account exists, a password reset email has been sent.".to_owned(),
+    }))
+}
+
+/// Handles the admin forgot password logic.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ForgotPasswordRequest>,
+) -> Result<Json<PasswordResetResponse>, AppError> {
+    send_password_reset(&state, &payload.email, "Admin").await
+}
+
+/// A struct to represent the reset password request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, message = "Must not be empty."))]
+    pub token: String,
+    #[validate(custom = "validate_password_strength")]
+    pub password: String,
+}
+
+/// Handles the reset password logic.
+/// Resetting revokes every session and token issued to the user so far, including the reset token itself.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ResetPasswordRequest>,
+) -> Result<Json<PasswordResetResponse>, AppError> {
+    let token_data = verify_password_reset_jwt(&state.jwt_keys, &payload.token)
+        .map_err(|_| AppError::BadRequest("Invalid or expired password reset token.".to_owned()))?;
+
+    let user_id: i32 = token_data
+        .claims
+        .id
+        .parse()
+        .map_err(|_| AppError::BadRequest("Invalid user ID in token.".to_owned()))?;
+
+    let hashed_password = bcrypt::hash(payload.password, bcrypt::DEFAULT_COST)?;
+
+    // A reset token can only be used once: the password is only changed while the token
+    // version still matches the token, and the same update bumps it. Of two concurrent
+    // requests with the same token, only one updates a row.
+    let result = User::update_many()
+        .col_expr(user::Column::Password, Expr::value(hashed_password))
+        .col_expr(
+            user::Column::TokenVersion,
+            Expr::col(user::Column::TokenVersion).add(1),
+        )
+        .col_expr(user::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(user::Column::Id.eq(user_id))
+        .filter(user::Column::TokenVersion.eq(token_data.claims.ver))
+        .exec(&state.db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        return Err(AppError::BadRequest(
+            "Invalid or expired password reset token.".to_owned(),
+        ));
+    }
+
+    // Log the user out everywhere
+    revoke_all_sessions(&state.db, &state.cache, user_id).await?;
+
+    Ok(Json(PasswordResetResponse {
+        status: true,
+        message: "Password has been reset. Please log in with your new password.".to_owned(),
+    }))
+}
@@ -1,34 +1,274 @@
-use crate::config::redis;
-use axum::Json;
+use std::collections::HashSet;
 
-pub async fn index() -> Json<String> {
-    let cache_key = "categories";
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+use validator::Validate;
 
-    //  Try to get from cache
-    if let Ok(Some(cached)) = redis::get_value(cache_key).await {
-        println!("Cache hit!");
-        return Json(cached); // return cached value
+use crate::config::app_state::AppState;
+use crate::config::cache::{Cache, CacheExt};
+use crate::config::validation::{ValidatedJson, validate_slug};
+use crate::errors::{AppError, conflict_on_unique_violation};
+use crate::models::{category, category::Entity as Category};
+use crate::utils::helper::slugify;
+
+/// Redis key of the cached category list.
+pub const CATEGORIES_CACHE_KEY: &str = "categories";
+/// Lifetime of the cached category list (seconds).
+const CATEGORIES_CACHE_TTL: usize = 600;
+
+/// A struct to represent the create and update category request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CategoryRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Must be between 1 and 255 characters long."
+    ))]
+    pub name: String,
+    /// Derived from the name when missing.
+    #[validate(custom = "validate_slug")]
+    pub slug: Option<String>,
+    pub parent_id: Option<i32>,
+    #[validate(length(max = 5000, message = "Must be at most 5000 characters long."))]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub sort_order: i32,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// A struct to represent the list of categories.
+#[derive(Debug, Serialize)]
+pub struct CategoryListResponse {
+    pub status: bool,
+    pub data: Vec<category::Model>,
+}
+
+/// A struct to represent a single category.
+#[derive(Debug, Serialize)]
+pub struct CategoryResponse {
+    pub status: bool,
+    pub data: category::Model,
+}
+
+/// A struct to represent a successful category deletion response.
+#[derive(Debug, Serialize)]
+pub struct CategoryDeleteResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Drops the cached category list after a write.
+/// A failure only delays the change for readers until the cache expires, so it is logged
+/// instead of failing the request.
+pub async fn invalidate_categories_cache(cache: &dyn Cache) {
+    if let Err(e) = cache.delete(CATEGORIES_CACHE_KEY).await {
+        error!("Failed to invalidate the categories cache: {}", e);
+    }
+}
+
+/// Loads a category or returns a 404.
+async fn find_category(db: &DatabaseConnection, id: i32) -> Result<category::Model, AppError> {
+    Category::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Category not found.".to_owned()))
+}
+
+/// Returns the slug to store for a category.
+/// An explicit slug must be free; a slug derived from the name gets a numeric suffix until it is.
+async fn resolve_slug(
+    db: &DatabaseConnection,
+    payload: &CategoryRequest,
+    category_id: Option<i32>,
+) -> Result<String, AppError> {
+    let slug_taken = |slug: String| async move {
+        let mut query = Category::find().filter(category::Column::Slug.eq(slug));
+        if let Some(id) = category_id {
+            query = query.filter(category::Column::Id.ne(id));
+        }
+        Ok::<bool, AppError>(query.one(db).await?.is_some())
+    };
+
+    if let Some(slug) = &payload.slug {
+        if slug_taken(slug.clone()).await? {
+            return Err(AppError::Conflict("Slug is already in use.".to_owned()));
+        }
+        return Ok(slug.clone());
+    }
+
+    let base = slugify(&payload.name);
+    if base.is_empty() {
+        return Err(AppError::BadRequest(
+            "A slug cannot be derived from this name. Please provide one.".to_owned(),
+        ));
     }
 
-    //  If not in cache, fetch from "DB" (here we use static example)
-    let categories = vec!["Electronics", "Books", "Clothing"];
-    let value = serde_json::to_string(&categories).unwrap();
+    let mut slug = base.clone();
+    let mut suffix = 2;
+    while slug_taken(slug.clone()).await? {
+        slug = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+    Ok(slug)
+}
 
-    //  Set value in cache for 60 seconds
-    let _ = redis::set_value(cache_key, &value, 600).await;
+/// Checks that the parent exists and, for an existing category, that the move would not
+/// create a cycle in the tree.
+async fn ensure_valid_parent(
+    db: &DatabaseConnection,
+    parent_id: Option<i32>,
+    category_id: Option<i32>,
+) -> Result<(), AppError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
 
-    println!("Cache miss! Setting cache.");
-    Json(value)
+    let mut visited = HashSet::new();
+    let mut current = Some(parent_id);
+    while let Some(id) = current {
+        if Some(id) == category_id {
+            return Err(AppError::BadRequest(
+                "A category cannot be moved under itself or one of its descendants.".to_owned(),
+            ));
+        }
+        if !visited.insert(id) {
+            break;
+        }
+
+        let ancestor = Category::find_by_id(id).one(db).await?;
+        match ancestor {
+            Some(ancestor) => current = ancestor.parent_id,
+            None if id == parent_id => {
+                return Err(AppError::BadRequest(
+                    "Parent category not found.".to_owned(),
+                ));
+            }
+            None => break,
+        }
+    }
+    Ok(())
 }
 
-pub async fn store() -> &'static str {
-    "Category Store"
+/// Lists all categories, ordered for display. The list is served from the cache when cached.
+pub async fn index(State(state): State<AppState>) -> Result<Json<CategoryListResponse>, AppError> {
+    let data = state
+        .cache
+        .get_or_set_json(CATEGORIES_CACHE_KEY, CATEGORIES_CACHE_TTL, || async {
+            debug!("Categories cache miss");
+            Category::find()
+                .order_by_asc(category::Column::SortOrder)
+                .order_by_asc(category::Column::Name)
+                .all(&state.db)
+                .await
+        })
+        .await?;
+
+    Ok(Json(CategoryListResponse { status: true, data }))
 }
 
-pub async fn update() -> &'static str {
-    "Admin Update"
+/// Shows a single category.
+pub async fn show(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> Result<Json<CategoryResponse>, AppError> {
+    let data = find_category(&db, id).await?;
+    Ok(Json(CategoryResponse { status: true, data }))
 }
 
-pub async fn destroy() -> &'static str {
-    "Admin Delete"
+/// Creates a category.
+pub async fn store(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<CategoryRequest>,
+) -> Result<Json<CategoryResponse>, AppError> {
+    ensure_valid_parent(&state.db, payload.parent_id, None).await?;
+    let slug = resolve_slug(&state.db, &payload, None).await?;
+
+    let now = Utc::now().naive_utc();
+    let new_category = category::ActiveModel {
+        name: Set(payload.name),
+        slug: Set(slug),
+        parent_id: Set(payload.parent_id),
+        description: Set(payload.description),
+        sort_order: Set(payload.sort_order),
+        active: Set(payload.active),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let data = new_category
+        .insert(&state.db)
+        .await
+        .map_err(conflict_on_unique_violation("Slug is already in use."))?;
+
+    invalidate_categories_cache(&state.cache).await;
+    info!("Category created: {} ({})", data.slug, data.id);
+
+    Ok(Json(CategoryResponse { status: true, data }))
+}
+
+/// Replaces a category's fields.
+pub async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<CategoryRequest>,
+) -> Result<Json<CategoryResponse>, AppError> {
+    let category_model = find_category(&state.db, id).await?;
+    ensure_valid_parent(&state.db, payload.parent_id, Some(id)).await?;
+
+    // Keep the current slug when the name still produces it
+    let slug = match &payload.slug {
+        None if slugify(&payload.name) == slugify(&category_model.name) => {
+            category_model.slug.clone()
+        }
+        _ => resolve_slug(&state.db, &payload, Some(id)).await?,
+    };
+
+    let mut category_active_model: category::ActiveModel = category_model.into();
+    category_active_model.name = Set(payload.name);
+    category_active_model.slug = Set(slug);
+    category_active_model.parent_id = Set(payload.parent_id);
+    category_active_model.description = Set(payload.description);
+    category_active_model.sort_order = Set(payload.sort_order);
+    category_active_model.active = Set(payload.active);
+    category_active_model.updated_at = Set(Utc::now().naive_utc());
+    let data = category_active_model
+        .update(&state.db)
+        .await
+        .map_err(conflict_on_unique_violation("Slug is already in use."))?;
+
+    invalidate_categories_cache(&state.cache).await;
+    info!("Category updated: {} ({})", data.slug, data.id);
+
+    Ok(Json(CategoryResponse { status: true, data }))
+}
+
+/// Deletes a category. Its children become top-level categories.
+pub async fn destroy(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<CategoryDeleteResponse>, AppError> {
+    let category_model = find_category(&state.db, id).await?;
+    category_model.delete(&state.db).await?;
+
+    invalidate_categories_cache(&state.cache).await;
+    info!("Category deleted: {}", id);
+
+    Ok(Json(CategoryDeleteResponse {
+        status: true,
+        message: "Category deleted.".to_owned(),
+    }))
 }
@@ -1,14 +1,8 @@
-use axum::{Json, http::StatusCode};
+use axum::Json;
 use serde::Serialize;
 
 use crate::config::auth_bearer::AuthBearer;
-
-/// A struct to represent a custom error response.
-#[derive(Debug, Serialize)]
-pub struct AuthErrorResponse {
-    pub status: bool,
-    pub message: String,
-}
+use crate::errors::AppError;
 
 #[derive(Debug, Serialize)]
 pub struct AdminDashboardResponse {
@@ -17,15 +11,11 @@ pub struct AdminDashboardResponse {
 }
 
 /// Handles the admin dashboard logic.
-pub async fn admin_dashboard(
-    claims: AuthBearer,
-) -> Result<Json<AdminDashboardResponse>, (StatusCode, Json<AuthErrorResponse>)> {
+pub async fn admin_dashboard(claims: AuthBearer) -> Result<Json<AdminDashboardResponse>, AppError> {
     if claims.0.role != "Admin" {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Access denied. Only administrators can view this page.".to_string(),
-        };
-        return Err((StatusCode::FORBIDDEN, Json(error_response)));
+        return Err(AppError::Forbidden(
+            "Access denied. Only administrators can view this page.".to_owned(),
+        ));
     }
 
     // You can now use the `claims.sub` (user ID) to fetch specific user data.
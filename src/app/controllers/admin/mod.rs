@@ -1,5 +1,9 @@
+pub mod auth_controller;
 pub mod category_controller;
 pub mod dashboard_controller;
-pub mod auth_controller;
-
+pub mod order_controller;
+pub mod product_controller;
+pub mod session_controller;
 
+pub mod two_factor_controller;
+pub mod user_controller;
@@ -0,0 +1,269 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::NaiveDate;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, LoaderTrait, PaginatorTrait, QueryFilter,
+    QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::app::controllers::admin::product_controller::page_params;
+use crate::app::controllers::customer::checkout_controller::OrderData;
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::order_workflow::transition_order;
+use crate::config::payments;
+use crate::config::validation::ValidatedJson;
+use crate::errors::AppError;
+use crate::models::{
+    order, order::Entity as Order, order_item, order_item::Entity as OrderItem,
+    order_status_history, order_status_history::Entity as OrderStatusHistory, payment,
+    payment::Entity as Payment, user::Entity as User,
+};
+
+/// Checks that a status is one of the order statuses.
+fn validate_order_status(status: &str) -> Result<(), ValidationError> {
+    if order::STATUSES.contains(&status) {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("order_status");
+    error.message = Some(format!("Must be one of: {}.", order::STATUSES.join(", ")).into());
+    Err(error)
+}
+
+/// A struct to represent the query string of the order list.
+/// `from` and `to` are inclusive dates (YYYY-MM-DD) on which the order was placed.
+#[derive(Debug, Deserialize)]
+pub struct OrderQuery {
+    pub status: Option<String>,
+    pub user_id: Option<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+/// A struct to represent the update order status request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateOrderStatusRequest {
+    #[validate(custom = "validate_order_status")]
+    pub status: String,
+    #[validate(length(max = 1000, message = "Must be at most 1000 characters long."))]
+    pub note: Option<String>,
+}
+
+/// A struct to represent the customer who placed an order.
+#[derive(Debug, Serialize)]
+pub struct OrderCustomer {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+}
+
+/// An order with its items, status history, payments and customer.
+#[derive(Debug, Serialize)]
+pub struct OrderDetails {
+    #[serde(flatten)]
+    pub order: OrderData,
+    pub history: Vec<order_status_history::Model>,
+    pub payments: Vec<payment::Model>,
+    pub customer: Option<OrderCustomer>,
+}
+
+/// A struct to represent a page of orders.
+#[derive(Debug, Serialize)]
+pub struct OrderListResponse {
+    pub status: bool,
+    pub data: Vec<OrderData>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
+
+/// A struct to represent a single order.
+#[derive(Debug, Serialize)]
+pub struct OrderDetailsResponse {
+    pub status: bool,
+    pub data: OrderDetails,
+}
+
+/// A struct to represent a successful order status change.
+#[derive(Debug, Serialize)]
+pub struct OrderStatusResponse {
+    pub status: bool,
+    pub message: String,
+    pub data: OrderDetails,
+}
+
+/// Loads an order with its items, history, payments and customer or returns a 404.
+async fn find_order_details(db: &DatabaseConnection, id: i32) -> Result<OrderDetails, AppError> {
+    let order_model = Order::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found.".to_owned()))?;
+
+    let items = OrderItem::find()
+        .filter(order_item::Column::OrderId.eq(id))
+        .order_by_asc(order_item::Column::Id)
+        .all(db)
+        .await?;
+    let history = OrderStatusHistory::find()
+        .filter(order_status_history::Column::OrderId.eq(id))
+        .order_by_asc(order_status_history::Column::Id)
+        .all(db)
+        .await?;
+    let payments = Payment::find()
+        .filter(payment::Column::OrderId.eq(id))
+        .order_by_asc(payment::Column::Id)
+        .all(db)
+        .await?;
+    let customer = User::find_by_id(order_model.user_id)
+        .one(db)
+        .await?
+        .map(|user_model| OrderCustomer {
+            id: user_model.id,
+            name: user_model.name,
+            email: user_model.email,
+        });
+
+    Ok(OrderDetails {
+        order: OrderData {
+            order: order_model,
+            items,
+        },
+        history,
+        payments,
+        customer,
+    })
+}
+
+/// Lists orders, newest first, filtered by status, customer and placement date.
+pub async fn index(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<OrderQuery>,
+) -> Result<Json<OrderListResponse>, AppError> {
+    let (page, per_page) = page_params(query.page, query.per_page);
+
+    let mut select = Order::find().order_by_desc(order::Column::Id);
+    if let Some(status) = query.status {
+        select = select.filter(order::Column::Status.eq(status));
+    }
+    if let Some(user_id) = query.user_id {
+        select = select.filter(order::Column::UserId.eq(user_id));
+    }
+    if let Some(from) = query.from {
+        select = select.filter(order::Column::CreatedAt.gte(from.and_time(Default::default())));
+    }
+    if let Some(to) = query.to.and_then(|to| to.succ_opt()) {
+        select = select.filter(order::Column::CreatedAt.lt(to.and_time(Default::default())));
+    }
+
+    let paginator = select.paginate(&db, per_page);
+    let total = paginator.num_items().await?;
+    let orders = paginator.fetch_page(page - 1).await?;
+    let items = orders
+        .load_many(OrderItem::find().order_by_asc(order_item::Column::Id), &db)
+        .await?;
+    let data = orders
+        .into_iter()
+        .zip(items)
+        .map(|(order, items)| OrderData { order, items })
+        .collect();
+
+    Ok(Json(OrderListResponse {
+        status: true,
+        data,
+        page,
+        per_page,
+        total,
+    }))
+}
+
+/// Shows a single order with its items, status history, payments and customer.
+pub async fn show(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> Result<Json<OrderDetailsResponse>, AppError> {
+    let data = find_order_details(&db, id).await?;
+    Ok(Json(OrderDetailsResponse { status: true, data }))
+}
+
+/// Moves an order to a new status. Only the transitions of the order workflow are allowed;
+/// the customer is notified by email.
+/// Paid and refunded follow from the payment, so they are set by capturing or refunding it
+/// (or by the provider's webhook) and not here. Cancelling also cancels the open payment.
+pub async fn update_status(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateOrderStatusRequest>,
+) -> Result<Json<OrderStatusResponse>, AppError> {
+    let changed_by = claims.0.id.parse().ok();
+    match payload.status.as_str() {
+        order::STATUS_PAID | order::STATUS_REFUNDED => {
+            return Err(AppError::BadRequest(format!(
+                "An order becomes {} through its payment. Capture or refund the payment instead.",
+                payload.status
+            )));
+        }
+        order::STATUS_CANCELLED => {
+            payments::cancel_order(&state, id, payload.note, changed_by).await?;
+        }
+        _ => {
+            transition_order(
+                &state.db,
+                &state.queue,
+                id,
+                &payload.status,
+                payload.note,
+                changed_by,
+            )
+            .await?;
+        }
+    }
+
+    let data = find_order_details(&state.db, id).await?;
+    Ok(Json(OrderStatusResponse {
+        status: true,
+        message: format!("Order is now {}.", data.order.order.status),
+        data,
+    }))
+}
+
+/// Captures the authorized payment of an order, which marks the order as paid.
+pub async fn capture_payment(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    Path(id): Path<i32>,
+) -> Result<Json<OrderStatusResponse>, AppError> {
+    let changed_by = claims.0.id.parse().ok();
+    payments::capture_payment(&state, id, changed_by).await?;
+
+    let data = find_order_details(&state.db, id).await?;
+    Ok(Json(OrderStatusResponse {
+        status: true,
+        message: "Payment captured.".to_owned(),
+        data,
+    }))
+}
+
+/// Refunds the captured payment of an order at the provider, which marks the order as refunded.
+pub async fn refund_payment(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    Path(id): Path<i32>,
+) -> Result<Json<OrderStatusResponse>, AppError> {
+    let changed_by = claims.0.id.parse().ok();
+    payments::refund_payment(&state, id, changed_by).await?;
+
+    let data = find_order_details(&state.db, id).await?;
+    Ok(Json(OrderStatusResponse {
+        status: true,
+        message: "Payment refunded.".to_owned(),
+        data,
+    }))
+}
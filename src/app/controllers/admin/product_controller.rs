@@ -0,0 +1,581 @@
+use std::collections::{BTreeSet, HashMap};
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::Utc;
+use sea_orm::sea_query::Query as SubQuery;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    LoaderTrait, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, Select, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use validator::{Validate, ValidationError};
+
+use crate::config::validation::{ValidatedJson, validate_slug};
+use crate::errors::{AppError, conflict_on_unique_violation};
+use crate::models::{
+    category, category::Entity as Category, product, product::Entity as Product, product_category,
+    product_category::Entity as ProductCategory, product_variant,
+    product_variant::Entity as ProductVariant,
+};
+use crate::utils::helper::slugify;
+
+/// Number of products per page when the query does not ask for one.
+pub const DEFAULT_PER_PAGE: u64 = 20;
+/// Largest page size a query can ask for.
+pub const MAX_PER_PAGE: u64 = 100;
+
+/// Checks that a status is one of the product statuses.
+fn validate_product_status(status: &str) -> Result<(), ValidationError> {
+    if product::STATUSES.contains(&status) {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("product_status");
+    error.message = Some(format!("Must be one of: {}.", product::STATUSES.join(", ")).into());
+    Err(error)
+}
+
+fn default_status() -> String {
+    product::STATUS_DRAFT.to_owned()
+}
+
+/// A struct to represent a product variant in a request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct VariantRequest {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Must be between 1 and 64 characters long."
+    ))]
+    pub sku: String,
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Must be between 1 and 255 characters long."
+    ))]
+    pub name: String,
+    /// Price in minor units (e.g. cents).
+    #[validate(range(min = 0, message = "Must not be negative."))]
+    pub price: i64,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Must not be negative."))]
+    pub stock: i32,
+}
+
+/// A struct to represent the create product request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateProductRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Must be between 1 and 255 characters long."
+    ))]
+    pub name: String,
+    /// Derived from the name when missing.
+    #[validate(custom = "validate_slug")]
+    pub slug: Option<String>,
+    #[validate(length(max = 5000, message = "Must be at most 5000 characters long."))]
+    pub description: Option<String>,
+    #[serde(default = "default_status")]
+    #[validate(custom = "validate_product_status")]
+    pub status: String,
+    #[serde(default)]
+    pub category_ids: Vec<i32>,
+    #[serde(default)]
+    #[validate]
+    pub variants: Vec<VariantRequest>,
+}
+
+/// A struct to represent the update product request body.
+/// Variants are managed through their own endpoints.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProductRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Must be between 1 and 255 characters long."
+    ))]
+    pub name: String,
+    /// Derived from the name when missing.
+    #[validate(custom = "validate_slug")]
+    pub slug: Option<String>,
+    #[validate(length(max = 5000, message = "Must be at most 5000 characters long."))]
+    pub description: Option<String>,
+    #[validate(custom = "validate_product_status")]
+    pub status: String,
+    #[serde(default)]
+    pub category_ids: Vec<i32>,
+}
+
+/// The query parameters of the admin product list.
+#[derive(Debug, Deserialize)]
+pub struct ProductQuery {
+    pub status: Option<String>,
+    pub category_id: Option<i32>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+/// A product with its variants and categories.
+#[derive(Debug, Serialize)]
+pub struct ProductData {
+    #[serde(flatten)]
+    pub product: product::Model,
+    pub variants: Vec<product_variant::Model>,
+    pub categories: Vec<category::Model>,
+}
+
+/// A struct to represent one page of products.
+#[derive(Debug, Serialize)]
+pub struct ProductListResponse {
+    pub status: bool,
+    pub data: Vec<ProductData>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
+
+/// A struct to represent a single product.
+#[derive(Debug, Serialize)]
+pub struct ProductResponse {
+    pub status: bool,
+    pub data: ProductData,
+}
+
+/// A struct to represent a single product variant.
+#[derive(Debug, Serialize)]
+pub struct VariantResponse {
+    pub status: bool,
+    pub data: product_variant::Model,
+}
+
+/// A struct to represent a successful product or variant deletion response.
+#[derive(Debug, Serialize)]
+pub struct ProductDeleteResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Returns the 1-based page number and page size asked for, within bounds.
+pub fn page_params(page: Option<u64>, per_page: Option<u64>) -> (u64, u64) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    (page, per_page)
+}
+
+/// Restricts a product query to products linked to any of the given categories.
+pub fn in_categories(select: Select<Product>, category_ids: Vec<i32>) -> Select<Product> {
+    select.filter(
+        product::Column::Id.in_subquery(
+            SubQuery::select()
+                .column(product_category::Column::ProductId)
+                .from(ProductCategory)
+                .and_where(product_category::Column::CategoryId.is_in(category_ids))
+                .to_owned(),
+        ),
+    )
+}
+
+/// Loads the variants and categories of the given products.
+pub async fn load_product_data(
+    db: &DatabaseConnection,
+    products: Vec<product::Model>,
+) -> Result<Vec<ProductData>, DbErr> {
+    let variants = products
+        .load_many(
+            ProductVariant::find().order_by_asc(product_variant::Column::Id),
+            db,
+        )
+        .await?;
+
+    let product_ids: Vec<i32> = products.iter().map(|p| p.id).collect();
+    let links = ProductCategory::find()
+        .filter(product_category::Column::ProductId.is_in(product_ids))
+        .all(db)
+        .await?;
+    let category_ids: BTreeSet<i32> = links.iter().map(|link| link.category_id).collect();
+    let categories: HashMap<i32, category::Model> = Category::find()
+        .filter(category::Column::Id.is_in(category_ids))
+        .order_by_asc(category::Column::SortOrder)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|category| (category.id, category))
+        .collect();
+
+    let data = products
+        .into_iter()
+        .zip(variants)
+        .map(|(product, variants)| {
+            let categories = links
+                .iter()
+                .filter(|link| link.product_id == product.id)
+                .filter_map(|link| categories.get(&link.category_id).cloned())
+                .collect();
+            ProductData {
+                product,
+                variants,
+                categories,
+            }
+        })
+        .collect();
+    Ok(data)
+}
+
+/// Loads a product with its variants and categories or returns a 404.
+async fn find_product_data(db: &DatabaseConnection, id: i32) -> Result<ProductData, AppError> {
+    let product_model = Product::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found.".to_owned()))?;
+
+    let mut data = load_product_data(db, vec![product_model]).await?;
+    Ok(data.remove(0))
+}
+
+/// Loads a variant of the given product or returns a 404.
+async fn find_variant(
+    db: &DatabaseConnection,
+    product_id: i32,
+    variant_id: i32,
+) -> Result<product_variant::Model, AppError> {
+    ProductVariant::find_by_id(variant_id)
+        .filter(product_variant::Column::ProductId.eq(product_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Variant not found.".to_owned()))
+}
+
+/// Returns the slug to store for a product.
+/// An explicit slug must be free; a slug derived from the name gets a numeric suffix until it is.
+/// A concurrent request can still take it before the write, which then fails with a 409 too.
+async fn resolve_slug<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+    slug: Option<&str>,
+    product_id: Option<i32>,
+) -> Result<String, AppError> {
+    let slug_taken = |slug: String| async move {
+        let mut query = Product::find().filter(product::Column::Slug.eq(slug));
+        if let Some(id) = product_id {
+            query = query.filter(product::Column::Id.ne(id));
+        }
+        Ok::<bool, AppError>(query.one(db).await?.is_some())
+    };
+
+    if let Some(slug) = slug {
+        if slug_taken(slug.to_owned()).await? {
+            return Err(AppError::Conflict("Slug is already in use.".to_owned()));
+        }
+        return Ok(slug.to_owned());
+    }
+
+    let base = slugify(name);
+    if base.is_empty() {
+        return Err(AppError::BadRequest(
+            "A slug cannot be derived from this name. Please provide one.".to_owned(),
+        ));
+    }
+
+    let mut slug = base.clone();
+    let mut suffix = 2;
+    while slug_taken(slug.clone()).await? {
+        slug = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+    Ok(slug)
+}
+
+/// Rejects a SKU that is already used by another variant.
+/// Like the slug, a SKU taken concurrently is caught by the unique index on write.
+async fn ensure_sku_available<C: ConnectionTrait>(
+    db: &C,
+    sku: &str,
+    variant_id: Option<i32>,
+) -> Result<(), AppError> {
+    let mut query = ProductVariant::find().filter(product_variant::Column::Sku.eq(sku));
+    if let Some(id) = variant_id {
+        query = query.filter(product_variant::Column::Id.ne(id));
+    }
+
+    if query.one(db).await?.is_some() {
+        return Err(AppError::Conflict(format!(
+            "SKU {} is already in use.",
+            sku
+        )));
+    }
+    Ok(())
+}
+
+/// Replaces the categories a product is linked to.
+async fn set_product_categories<C: ConnectionTrait>(
+    db: &C,
+    product_id: i32,
+    category_ids: &[i32],
+) -> Result<(), AppError> {
+    let category_ids: BTreeSet<i32> = category_ids.iter().copied().collect();
+
+    let found = Category::find()
+        .filter(category::Column::Id.is_in(category_ids.clone()))
+        .count(db)
+        .await?;
+    if found != category_ids.len() as u64 {
+        return Err(AppError::BadRequest(
+            "One or more categories do not exist.".to_owned(),
+        ));
+    }
+
+    ProductCategory::delete_many()
+        .filter(product_category::Column::ProductId.eq(product_id))
+        .exec(db)
+        .await?;
+
+    if !category_ids.is_empty() {
+        let links = category_ids
+            .into_iter()
+            .map(|category_id| product_category::ActiveModel {
+                product_id: Set(product_id),
+                category_id: Set(category_id),
+            });
+        ProductCategory::insert_many(links).exec(db).await?;
+    }
+    Ok(())
+}
+
+/// Lists products, optionally filtered by status and category.
+pub async fn index(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<ProductQuery>,
+) -> Result<Json<ProductListResponse>, AppError> {
+    let (page, per_page) = page_params(query.page, query.per_page);
+
+    let mut select = Product::find().order_by_desc(product::Column::Id);
+    if let Some(status) = query.status {
+        select = select.filter(product::Column::Status.eq(status));
+    }
+    if let Some(category_id) = query.category_id {
+        select = in_categories(select, vec![category_id]);
+    }
+
+    let paginator = select.paginate(&db, per_page);
+    let total = paginator.num_items().await?;
+    let products = paginator.fetch_page(page - 1).await?;
+    let data = load_product_data(&db, products).await?;
+
+    Ok(Json(ProductListResponse {
+        status: true,
+        data,
+        page,
+        per_page,
+        total,
+    }))
+}
+
+/// Shows a single product with its variants and categories.
+pub async fn show(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> Result<Json<ProductResponse>, AppError> {
+    let data = find_product_data(&db, id).await?;
+    Ok(Json(ProductResponse { status: true, data }))
+}
+
+/// Creates a product with its variants and category links in one transaction.
+pub async fn store(
+    State(db): State<DatabaseConnection>,
+    ValidatedJson(payload): ValidatedJson<CreateProductRequest>,
+) -> Result<Json<ProductResponse>, AppError> {
+    let mut skus = BTreeSet::new();
+    if let Some(variant) = payload.variants.iter().find(|v| !skus.insert(&v.sku)) {
+        return Err(AppError::BadRequest(format!(
+            "SKU {} is used more than once.",
+            variant.sku
+        )));
+    }
+
+    let txn = db.begin().await?;
+
+    let slug = resolve_slug(&txn, &payload.name, payload.slug.as_deref(), None).await?;
+    let now = Utc::now().naive_utc();
+    let product_model = product::ActiveModel {
+        name: Set(payload.name),
+        slug: Set(slug),
+        description: Set(payload.description),
+        status: Set(payload.status),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(conflict_on_unique_violation("Slug is already in use."))?;
+
+    for variant in payload.variants {
+        ensure_sku_available(&txn, &variant.sku, None).await?;
+        let sku_in_use = format!("SKU {} is already in use.", variant.sku);
+        product_variant::ActiveModel {
+            product_id: Set(product_model.id),
+            sku: Set(variant.sku),
+            name: Set(variant.name),
+            price: Set(variant.price),
+            stock: Set(variant.stock),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(conflict_on_unique_violation(sku_in_use))?;
+    }
+
+    set_product_categories(&txn, product_model.id, &payload.category_ids).await?;
+    txn.commit().await?;
+
+    info!(
+        "Product created: {} ({})",
+        product_model.slug, product_model.id
+    );
+
+    let data = find_product_data(&db, product_model.id).await?;
+    Ok(Json(ProductResponse { status: true, data }))
+}
+
+/// Replaces a product's fields and category links.
+pub async fn update(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateProductRequest>,
+) -> Result<Json<ProductResponse>, AppError> {
+    let product_model = Product::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found.".to_owned()))?;
+
+    let txn = db.begin().await?;
+
+    // Keep the current slug when the name still produces it
+    let slug = match &payload.slug {
+        None if slugify(&payload.name) == slugify(&product_model.name) => {
+            product_model.slug.clone()
+        }
+        slug => resolve_slug(&txn, &payload.name, slug.as_deref(), Some(id)).await?,
+    };
+
+    let mut product_active_model: product::ActiveModel = product_model.into();
+    product_active_model.name = Set(payload.name);
+    product_active_model.slug = Set(slug);
+    product_active_model.description = Set(payload.description);
+    product_active_model.status = Set(payload.status);
+    product_active_model.updated_at = Set(Utc::now().naive_utc());
+    product_active_model
+        .update(&txn)
+        .await
+        .map_err(conflict_on_unique_violation("Slug is already in use."))?;
+
+    set_product_categories(&txn, id, &payload.category_ids).await?;
+    txn.commit().await?;
+
+    info!("Product updated: {}", id);
+
+    let data = find_product_data(&db, id).await?;
+    Ok(Json(ProductResponse { status: true, data }))
+}
+
+/// Deletes a product with its variants and category links.
+pub async fn destroy(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> Result<Json<ProductDeleteResponse>, AppError> {
+    let product_model = Product::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found.".to_owned()))?;
+    product_model.delete(&db).await?;
+
+    info!("Product deleted: {}", id);
+
+    Ok(Json(ProductDeleteResponse {
+        status: true,
+        message: "Product deleted.".to_owned(),
+    }))
+}
+
+/// Adds a variant to a product.
+pub async fn store_variant(
+    State(db): State<DatabaseConnection>,
+    Path(product_id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<VariantRequest>,
+) -> Result<Json<VariantResponse>, AppError> {
+    Product::find_by_id(product_id)
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found.".to_owned()))?;
+    ensure_sku_available(&db, &payload.sku, None).await?;
+
+    let sku_in_use = format!("SKU {} is already in use.", payload.sku);
+    let now = Utc::now().naive_utc();
+    let data = product_variant::ActiveModel {
+        product_id: Set(product_id),
+        sku: Set(payload.sku),
+        name: Set(payload.name),
+        price: Set(payload.price),
+        stock: Set(payload.stock),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .map_err(conflict_on_unique_violation(sku_in_use))?;
+
+    info!("Variant created: {} ({})", data.sku, data.id);
+
+    Ok(Json(VariantResponse { status: true, data }))
+}
+
+/// Replaces a variant's fields.
+pub async fn update_variant(
+    State(db): State<DatabaseConnection>,
+    Path((product_id, variant_id)): Path<(i32, i32)>,
+    ValidatedJson(payload): ValidatedJson<VariantRequest>,
+) -> Result<Json<VariantResponse>, AppError> {
+    let variant_model = find_variant(&db, product_id, variant_id).await?;
+    ensure_sku_available(&db, &payload.sku, Some(variant_id)).await?;
+
+    let sku_in_use = format!("SKU {} is already in use.", payload.sku);
+    let mut variant_active_model: product_variant::ActiveModel = variant_model.into();
+    variant_active_model.sku = Set(payload.sku);
+    variant_active_model.name = Set(payload.name);
+    variant_active_model.price = Set(payload.price);
+    variant_active_model.stock = Set(payload.stock);
+    variant_active_model.updated_at = Set(Utc::now().naive_utc());
+    let data = variant_active_model
+        .update(&db)
+        .await
+        .map_err(conflict_on_unique_violation(sku_in_use))?;
+
+    info!("Variant updated: {} ({})", data.sku, data.id);
+
+    Ok(Json(VariantResponse { status: true, data }))
+}
+
+/// Deletes a variant.
+pub async fn destroy_variant(
+    State(db): State<DatabaseConnection>,
+    Path((product_id, variant_id)): Path<(i32, i32)>,
+) -> Result<Json<ProductDeleteResponse>, AppError> {
+    let variant_model = find_variant(&db, product_id, variant_id).await?;
+    variant_model.delete(&db).await?;
+
+    info!("Variant deleted: {}", variant_id);
+
+    Ok(Json(ProductDeleteResponse {
+        status: true,
+        message: "Variant deleted.".to_owned(),
+    }))
+}
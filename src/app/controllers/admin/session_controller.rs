@@ -0,0 +1,119 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::sessions::{revoke_all_sessions, revoke_session};
+use crate::errors::AppError;
+use crate::models::{session, session::Entity as Session};
+
+/// A serializable struct describing one active session.
+#[derive(Debug, Serialize)]
+pub struct SessionData {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub last_used_at: chrono::NaiveDateTime,
+    pub current: bool,
+}
+
+/// A struct to represent the list of active sessions.
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    pub status: bool,
+    pub data: Vec<SessionData>,
+}
+
+/// A struct to represent a successful session revocation response.
+#[derive(Debug, Serialize)]
+pub struct SessionRevokeResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Parses the user id carried by the token claims.
+fn claims_user_id(claims: &AuthBearer) -> Result<i32, AppError> {
+    claims
+        .0
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))
+}
+
+/// Lists the active sessions of the authenticated user.
+pub async fn index(
+    State(db): State<DatabaseConnection>,
+    claims: AuthBearer,
+) -> Result<Json<SessionListResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+
+    let sessions = Session::find()
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::Revoked.eq(false))
+        .order_by_desc(session::Column::LastUsedAt)
+        .all(&db)
+        .await?;
+
+    let data = sessions
+        .into_iter()
+        .map(|session| SessionData {
+            current: session.id == claims.0.sid,
+            id: session.id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+        })
+        .collect();
+
+    Ok(Json(SessionListResponse { status: true, data }))
+}
+
+/// Revokes one of the authenticated user's sessions.
+pub async fn destroy(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    Path(id): Path<String>,
+) -> Result<Json<SessionRevokeResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+
+    // Only the owner can revoke a session
+    let session_model = Session::find_by_id(id)
+        .filter(session::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found.".to_owned()))?;
+
+    revoke_session(
+        &state.db,
+        &state.cache,
+        &session_model.id,
+        state.config.access_token_ttl,
+    )
+    .await?;
+
+    Ok(Json(SessionRevokeResponse {
+        status: true,
+        message: "Session revoked.".to_owned(),
+    }))
+}
+
+/// Revokes every session of the authenticated user, including the current one.
+pub async fn logout_all(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+) -> Result<Json<SessionRevokeResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+
+    revoke_all_sessions(&state.db, &state.cache, user_id).await?;
+
+    Ok(Json(SessionRevokeResponse {
+        status: true,
+        message: "Logged out from all sessions.".to_owned(),
+    }))
+}
@@ -0,0 +1,249 @@
+use axum::{Json, extract::State};
+use bcrypt::verify;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use validator::Validate;
+
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::two_factor::{
+    decrypt_secret, delete_recovery_codes, encrypt_secret, generate_recovery_codes,
+    generate_secret, provisioning_uri, two_factor_required_for, verify_second_factor,
+    verify_totp_code,
+};
+use crate::config::validation::ValidatedJson;
+use crate::errors::AppError;
+use crate::models::{user, user::Entity as User};
+
+/// A struct to represent the secret handed out when enrolment starts.
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub status: bool,
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// A struct to represent a request carrying a TOTP (or recovery) code.
+#[derive(Debug, Deserialize, Validate)]
+pub struct TwoFactorCodeRequest {
+    #[validate(length(
+        min = 6,
+        max = 32,
+        message = "Must be between 6 and 32 characters long."
+    ))]
+    pub code: String,
+}
+
+/// A struct to represent the recovery codes shown after enrolment.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub status: bool,
+    pub message: String,
+    pub recovery_codes: Vec<String>,
+}
+
+/// A struct to represent the request to turn two-factor authentication off.
+#[derive(Debug, Deserialize, Validate)]
+pub struct DisableTwoFactorRequest {
+    #[validate(length(min = 1, message = "Must not be empty."))]
+    pub password: String,
+    #[validate(length(
+        min = 6,
+        max = 32,
+        message = "Must be between 6 and 32 characters long."
+    ))]
+    pub code: String,
+}
+
+/// A struct to represent a successful two-factor status change.
+#[derive(Debug, Serialize)]
+pub struct TwoFactorResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Loads the user the token was issued to.
+async fn find_token_user(
+    db: &DatabaseConnection,
+    claims: &AuthBearer,
+) -> Result<user::Model, AppError> {
+    let user_id: i32 = claims
+        .0
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))?;
+
+    User::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found.".to_owned()))
+}
+
+/// Starts TOTP enrolment: generates a new secret and returns it with its provisioning URI.
+/// The secret only becomes active once it is confirmed with a valid code.
+pub async fn setup(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+) -> Result<Json<TwoFactorSetupResponse>, AppError> {
+    let user_model = find_token_user(&state.db, &claims).await?;
+
+    if user_model.totp_enabled_at.is_some() {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled.".to_owned(),
+        ));
+    }
+
+    let secret = generate_secret();
+    let uri = provisioning_uri(&secret, &user_model.email)?;
+    let encrypted_secret = encrypt_secret(&state.config, &secret)?;
+
+    let mut user_active_model: user::ActiveModel = user_model.into();
+    user_active_model.totp_secret = Set(Some(encrypted_secret));
+    user_active_model.updated_at = Set(Utc::now().naive_utc());
+    user_active_model.update(&state.db).await?;
+
+    Ok(Json(TwoFactorSetupResponse {
+        status: true,
+        secret,
+        provisioning_uri: uri,
+    }))
+}
+
+/// Confirms TOTP enrolment with a code from the authenticator app and hands out recovery codes.
+pub async fn confirm(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    ValidatedJson(payload): ValidatedJson<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user_model = find_token_user(&state.db, &claims).await?;
+
+    if user_model.totp_enabled_at.is_some() {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled.".to_owned(),
+        ));
+    }
+
+    let Some(encrypted_secret) = user_model.totp_secret.clone() else {
+        return Err(AppError::BadRequest(
+            "Two-factor setup has not been started.".to_owned(),
+        ));
+    };
+
+    let secret = decrypt_secret(&state.config, &encrypted_secret)?;
+    let code_is_valid =
+        verify_totp_code(&state.cache, user_model.id, &secret, &payload.code).await?;
+
+    if !code_is_valid {
+        return Err(AppError::BadRequest(
+            "Invalid authentication code.".to_owned(),
+        ));
+    }
+
+    let user_id = user_model.id;
+    let mut user_active_model: user::ActiveModel = user_model.into();
+    user_active_model.totp_enabled_at = Set(Some(Utc::now().naive_utc()));
+    user_active_model.updated_at = Set(Utc::now().naive_utc());
+    user_active_model.update(&state.db).await?;
+
+    let recovery_codes = generate_recovery_codes(&state.db, user_id).await?;
+    info!(target: "security", user_id, "Two-factor authentication enabled");
+
+    Ok(Json(RecoveryCodesResponse {
+        status: true,
+        message: "Two-factor authentication enabled. Store these recovery codes somewhere safe."
+            .to_owned(),
+        recovery_codes,
+    }))
+}
+
+/// Replaces the recovery codes after checking a current code.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    ValidatedJson(payload): ValidatedJson<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user_model = find_token_user(&state.db, &claims).await?;
+    verify_enabled_factor(&state, &user_model, &payload.code).await?;
+
+    let recovery_codes = generate_recovery_codes(&state.db, user_model.id).await?;
+
+    Ok(Json(RecoveryCodesResponse {
+        status: true,
+        message: "New recovery codes created. The old codes no longer work.".to_owned(),
+        recovery_codes,
+    }))
+}
+
+/// Turns two-factor authentication off after checking the password and a current code.
+/// Not allowed while two-factor authentication is required for the user's role.
+pub async fn disable(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    ValidatedJson(payload): ValidatedJson<DisableTwoFactorRequest>,
+) -> Result<Json<TwoFactorResponse>, AppError> {
+    let user_model = find_token_user(&state.db, &claims).await?;
+
+    if two_factor_required_for(&state.config, &user_model.role) {
+        return Err(AppError::Forbidden(
+            "Two-factor authentication is required for your role.".to_owned(),
+        ));
+    }
+
+    let password_is_valid = verify(&payload.password, &user_model.password)?;
+    if !password_is_valid {
+        return Err(AppError::BadRequest("Password is incorrect.".to_owned()));
+    }
+
+    verify_enabled_factor(&state, &user_model, &payload.code).await?;
+
+    let user_id = user_model.id;
+    let mut user_active_model: user::ActiveModel = user_model.into();
+    user_active_model.totp_secret = Set(None);
+    user_active_model.totp_enabled_at = Set(None);
+    user_active_model.updated_at = Set(Utc::now().naive_utc());
+    user_active_model.update(&state.db).await?;
+
+    delete_recovery_codes(&state.db, user_id).await?;
+    info!(target: "security", user_id, "Two-factor authentication disabled");
+
+    Ok(Json(TwoFactorResponse {
+        status: true,
+        message: "Two-factor authentication disabled.".to_owned(),
+    }))
+}
+
+/// Checks a TOTP or recovery code against the user's enabled second factor.
+async fn verify_enabled_factor(
+    state: &AppState,
+    user_model: &user::Model,
+    code: &str,
+) -> Result<(), AppError> {
+    let Some(encrypted_secret) = user_model
+        .totp_secret
+        .as_deref()
+        .filter(|_| user_model.totp_enabled_at.is_some())
+    else {
+        return Err(AppError::BadRequest(
+            "Two-factor authentication is not enabled.".to_owned(),
+        ));
+    };
+
+    let code_is_valid = verify_second_factor(
+        &state.db,
+        &state.cache,
+        &state.config,
+        user_model.id,
+        encrypted_secret,
+        code,
+    )
+    .await?;
+
+    if !code_is_valid {
+        return Err(AppError::BadRequest(
+            "Invalid authentication code.".to_owned(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use tracing::info;
+
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::login_attempts::unlock_login;
+use crate::errors::AppError;
+use crate::models::user::Entity as User;
+
+/// A struct to represent a successful account unlock response.
+#[derive(Debug, Serialize)]
+pub struct UnlockResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Lifts the login lockout of an account, so its owner can log in again right away.
+/// The failure count of the owner's IP address stays, see `unlock_login`.
+pub async fn unlock(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    Path(id): Path<i32>,
+) -> Result<Json<UnlockResponse>, AppError> {
+    let user_model = User::find_by_id(id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found.".to_owned()))?;
+
+    unlock_login(&state.cache, &user_model.email).await?;
+
+    info!(
+        target: "security",
+        user_id = user_model.id,
+        unlocked_by = %claims.0.id,
+        "Account unlocked by an administrator"
+    );
+
+    Ok(Json(UnlockResponse {
+        status: true,
+        message: "Account unlocked.".to_owned(),
+    }))
+}
@@ -1,7 +1,152 @@
-use crate::models::user::Entity as User;
-use axum::{Extension, Json, http::StatusCode};
-use sea_orm::{DatabaseConnection, EntityTrait};
-use serde::Serialize;
+use anyhow::Result;
+use axum::{Json, extract::State};
+use bcrypt::verify;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use validator::Validate;
+
+// Logout, token refresh, email verification and password reset are role-agnostic, so the
+// customer tree shares the admin handlers and request/response types.
+use crate::app::controllers::admin::auth_controller::{
+    AuthResponse, ForgotPasswordRequest, LoginRequest, PasswordResetResponse, RegisterRequest,
+    ResendVerificationRequest, VerifyResponse, ensure_login_allowed, failed_login,
+    issue_auth_tokens, queue_verification_email, resend_verification, send_password_reset,
+    verify_dummy_password,
+};
+pub use crate::app::controllers::admin::auth_controller::{
+    logout, refresh_token, reset_password, verify_email,
+};
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::cart::{GuestCartToken, merge_guest_cart};
+use crate::config::client_info::ClientInfo;
+use crate::config::jwt::JwtClaims;
+use crate::config::login_attempts::clear_failed_logins;
+use crate::config::sessions::revoke_all_sessions;
+use crate::config::validation::{ValidatedJson, validate_password_strength};
+use crate::errors::AppError;
+use crate::models::{user, user::Entity as User};
+
+/// Handles the customer registration logic.
+pub async fn register(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    // Check if user with this email already exists
+    let existing_user = User::find()
+        .filter(user::Column::Email.eq(payload.email.clone()))
+        .one(&state.db)
+        .await?;
+
+    if existing_user.is_some() {
+        error!(
+            "Registration failed: Email already exists for customer: {}",
+            payload.email
+        );
+        return Err(AppError::Conflict("Email already exists.".to_owned()));
+    }
+
+    // Hash the password
+    let hashed_password = bcrypt::hash(payload.password, bcrypt::DEFAULT_COST)?;
+
+    // Create a new user active model with role "User"
+    let new_user = user::ActiveModel {
+        name: Set(payload.name),
+        email: Set(payload.email.clone()),
+        password: Set(hashed_password),
+        role: Set("User".to_owned()),
+        // `email_verified_at` is set to null by default
+        ..Default::default()
+    };
+
+    // Save the user to the database
+    let user_model = new_user.insert(&state.db).await?;
+
+    // Create JWT tokens (access and refresh)
+    let tokens = issue_auth_tokens(&state, &user_model, None, false, &client).await?;
+
+    // Send the verification email through RabbitMQ
+    queue_verification_email(
+        &state,
+        &user_model.id.to_string(),
+        &payload.email,
+        "customer",
+    )
+    .await?;
+    info!("Verification email send to: {}", payload.email);
+    // Return the authentication tokens
+    Ok(Json(tokens))
+}
+
+/// Handles resending the customer verification email.
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ResendVerificationRequest>,
+) -> Result<Json<VerifyResponse>, AppError> {
+    resend_verification(&state, &payload.email, "User", "customer").await
+}
+
+/// Handles the customer forgot password logic.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ForgotPasswordRequest>,
+) -> Result<Json<PasswordResetResponse>, AppError> {
+    send_password_reset(&state, &payload.email, "User").await
+}
+
+/// Handles the customer login logic.
+/// Only accounts with the "User" role can sign in through this endpoint.
+/// A guest cart sent along in the `X-Cart-Token` header is merged into the customer's cart.
+pub async fn login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    GuestCartToken(cart_token): GuestCartToken,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    ensure_login_allowed(&state, &payload.email, &client).await?;
+
+    // Find the customer by email
+    let user_model = User::find()
+        .filter(user::Column::Email.eq(&payload.email))
+        .filter(user::Column::Role.eq("User"))
+        .one(&state.db)
+        .await?;
+
+    let Some(user_model) = user_model else {
+        verify_dummy_password(&payload.password);
+        return Err(failed_login(&state, None, &payload.email, &client).await);
+    };
+
+    // Verify the password
+    let password_is_valid = verify(payload.password, &user_model.password)?;
+
+    if !password_is_valid {
+        return Err(failed_login(&state, Some(&user_model), &payload.email, &client).await);
+    }
+
+    // A failure to reset the counters must not block a valid login
+    if let Err(e) = clear_failed_logins(&state.cache, &payload.email).await {
+        error!("Failed to clear failed logins for {}: {}", payload.email, e);
+    }
+
+    // Create a new access token and a new refresh token.
+    let tokens = issue_auth_tokens(&state, &user_model, None, false, &client).await?;
+
+    // A failure to merge the guest cart must not block a valid login
+    if let Some(cart_token) = cart_token
+        && let Err(e) = merge_guest_cart(&state.db, &state.cache, user_model.id, &cart_token).await
+    {
+        error!(
+            "Failed to merge guest cart into the cart of user {}: {}",
+            user_model.id, e
+        );
+    }
+
+    Ok(Json(tokens))
+}
 
 // A serializable struct to format the JSON response for the user profile.
 #[derive(Debug, Serialize)]
@@ -20,38 +165,126 @@ pub struct UserProfileResponse {
     pub data: Option<UserProfileData>,
 }
 
-pub async fn profile(
-    Extension(db): Extension<DatabaseConnection>,
-) -> Result<Json<UserProfileResponse>, (StatusCode, String)> {
-    // Find the user with ID 1 in the database
-    let user_model = User::find_by_id(1).one(&db).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database Error: {}", e),
-        )
-    })?;
-
-    if let Some(user_data) = user_model {
-        // Create an instance of UserProfileData to exclude the password
-        let response_data = UserProfileData {
+impl From<user::Model> for UserProfileData {
+    fn from(user_data: user::Model) -> Self {
+        // Build the profile from the model so the password hash is never exposed
+        Self {
             id: user_data.id,
             name: user_data.name,
             email: user_data.email,
             role: user_data.role,
-        };
-
-        let response = UserProfileResponse {
-            status: "success".to_string(),
-            code: 200,
-            data: Some(response_data),
-        };
-        Ok(Json(response))
-    } else {
-        let response = UserProfileResponse {
-            status: "error".to_string(),
-            code: 404,
-            data: None,
-        };
-        Ok(Json(response))
+        }
     }
 }
+
+/// Loads the user that owns the bearer token of the current request.
+async fn find_authenticated_user(
+    db: &DatabaseConnection,
+    claims: &JwtClaims,
+) -> Result<user::Model, AppError> {
+    let user_id: i32 = claims
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))?;
+
+    let user_model = User::find_by_id(user_id).one(db).await?;
+
+    user_model.ok_or_else(|| AppError::NotFound("User not found.".to_owned()))
+}
+
+/// Returns the profile of the authenticated customer.
+pub async fn profile(
+    State(db): State<DatabaseConnection>,
+    AuthBearer(claims): AuthBearer,
+) -> Result<Json<UserProfileResponse>, AppError> {
+    let user_model = find_authenticated_user(&db, &claims).await?;
+
+    Ok(Json(UserProfileResponse {
+        status: "success".to_string(),
+        code: 200,
+        data: Some(user_model.into()),
+    }))
+}
+
+/// A struct to represent the profile update request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Must be between 1 and 255 characters long."
+    ))]
+    pub name: String,
+}
+
+/// Updates the name of the authenticated customer.
+pub async fn update_profile(
+    State(db): State<DatabaseConnection>,
+    AuthBearer(claims): AuthBearer,
+    ValidatedJson(payload): ValidatedJson<UpdateProfileRequest>,
+) -> Result<Json<UserProfileResponse>, AppError> {
+    let user_model = find_authenticated_user(&db, &claims).await?;
+
+    let mut user_active_model: user::ActiveModel = user_model.into();
+    user_active_model.name = Set(payload.name);
+    user_active_model.updated_at = Set(Utc::now().naive_utc());
+    let user_model = user_active_model.update(&db).await?;
+
+    Ok(Json(UserProfileResponse {
+        status: "success".to_string(),
+        code: 200,
+        data: Some(user_model.into()),
+    }))
+}
+
+/// A struct to represent the change password request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "Must not be empty."))]
+    pub current_password: String,
+    #[validate(custom = "validate_password_strength")]
+    pub new_password: String,
+}
+
+/// A struct to represent a successful password change response.
+#[derive(Debug, Serialize)]
+pub struct ChangePasswordResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Changes the password of the authenticated customer after checking the current one.
+/// All of the customer's sessions, including the current one, are logged out.
+pub async fn change_password(
+    State(state): State<AppState>,
+    AuthBearer(claims): AuthBearer,
+    ValidatedJson(payload): ValidatedJson<ChangePasswordRequest>,
+) -> Result<Json<ChangePasswordResponse>, AppError> {
+    let user_model = find_authenticated_user(&state.db, &claims).await?;
+
+    // Verify the current password
+    let password_is_valid = verify(payload.current_password, &user_model.password)?;
+
+    if !password_is_valid {
+        return Err(AppError::BadRequest(
+            "Current password is incorrect.".to_owned(),
+        ));
+    }
+
+    // Hash the new password
+    let hashed_password = bcrypt::hash(payload.new_password, bcrypt::DEFAULT_COST)?;
+
+    let user_id = user_model.id;
+    let mut user_active_model: user::ActiveModel = user_model.into();
+    user_active_model.password = Set(hashed_password);
+    user_active_model.updated_at = Set(Utc::now().naive_utc());
+    user_active_model.update(&state.db).await?;
+
+    // Tokens issued with the old password must stop working
+    revoke_all_sessions(&state.db, &state.cache, user_id).await?;
+
+    Ok(Json(ChangePasswordResponse {
+        status: true,
+        message: "Password changed successfully. Please log in again.".to_owned(),
+    }))
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::cart::{
+    GuestCartItem, GuestCartToken, MAX_CART_QUANTITY, add_cart_item, load_guest_cart,
+    save_guest_cart,
+};
+use crate::config::validation::ValidatedJson;
+use crate::errors::AppError;
+use crate::models::{
+    cart_item, cart_item::Entity as CartItem, product, product::Entity as Product, product_variant,
+    product_variant::Entity as ProductVariant,
+};
+
+/// A struct to represent the add-to-cart request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddCartItemRequest {
+    pub variant_id: i32,
+    #[validate(range(min = 1, max = 100, message = "Must be between 1 and 100."))]
+    pub quantity: i32,
+}
+
+/// A struct to represent the cart item update request body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCartItemRequest {
+    #[validate(range(min = 1, max = 100, message = "Must be between 1 and 100."))]
+    pub quantity: i32,
+}
+
+/// A serializable struct describing one line of a cart.
+#[derive(Debug, Serialize)]
+pub struct CartLine {
+    pub variant_id: i32,
+    pub sku: String,
+    pub product_name: String,
+    pub variant_name: String,
+    pub unit_price: i64,
+    pub quantity: i32,
+    pub line_total: i64,
+    pub in_stock: bool,
+}
+
+/// A serializable struct describing a cart and its total (minor units).
+#[derive(Debug, Serialize)]
+pub struct CartData {
+    pub items: Vec<CartLine>,
+    pub total: i64,
+}
+
+/// A struct to represent a cart response.
+/// Guest carts also return the token to send in the `X-Cart-Token` header.
+#[derive(Debug, Serialize)]
+pub struct CartResponse {
+    pub status: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_token: Option<String>,
+    pub data: CartData,
+}
+
+/// Parses the user id carried by the token claims.
+fn claims_user_id(claims: &AuthBearer) -> Result<i32, AppError> {
+    claims
+        .0
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))
+}
+
+/// Loads a variant of an active product or returns a 404.
+pub async fn find_purchasable_variant(
+    db: &DatabaseConnection,
+    variant_id: i32,
+) -> Result<(product_variant::Model, product::Model), AppError> {
+    let found = ProductVariant::find_by_id(variant_id)
+        .find_also_related(Product)
+        .one(db)
+        .await?;
+
+    match found {
+        Some((variant, Some(product))) if product.status == product::STATUS_ACTIVE => {
+            Ok((variant, product))
+        }
+        _ => Err(AppError::NotFound("Product not found.".to_owned())),
+    }
+}
+
+/// Rejects a quantity that is above the stock of the variant.
+/// Stock is only reserved at checkout, so this is a courtesy check.
+fn ensure_in_stock(variant: &product_variant::Model, quantity: i32) -> Result<(), AppError> {
+    if quantity > variant.stock {
+        return Err(AppError::Conflict(format!(
+            "Only {} of {} left in stock.",
+            variant.stock.max(0),
+            variant.sku
+        )));
+    }
+    Ok(())
+}
+
+/// Builds the cart lines for the given variant quantities.
+/// Variants that were deleted or whose product is no longer active are left out.
+async fn build_cart(db: &DatabaseConnection, items: Vec<(i32, i32)>) -> Result<CartData, AppError> {
+    let variant_ids: Vec<i32> = items.iter().map(|(variant_id, _)| *variant_id).collect();
+    let variants: HashMap<i32, (product_variant::Model, product::Model)> = ProductVariant::find()
+        .filter(product_variant::Column::Id.is_in(variant_ids))
+        .find_also_related(Product)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(variant, product)| {
+            product
+                .filter(|product| product.status == product::STATUS_ACTIVE)
+                .map(|product| (variant.id, (variant, product)))
+        })
+        .collect();
+
+    let lines: Vec<CartLine> = items
+        .into_iter()
+        .filter_map(|(variant_id, quantity)| {
+            let (variant, product) = variants.get(&variant_id)?;
+            Some(CartLine {
+                variant_id,
+                sku: variant.sku.clone(),
+                product_name: product.name.clone(),
+                variant_name: variant.name.clone(),
+                unit_price: variant.price,
+                quantity,
+                line_total: variant.price * i64::from(quantity),
+                in_stock: variant.stock >= quantity,
+            })
+        })
+        .collect();
+
+    let total = lines.iter().map(|line| line.line_total).sum();
+    Ok(CartData {
+        items: lines,
+        total,
+    })
+}
+
+/// Loads the cart items of a user, oldest first.
+pub async fn user_cart_items(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<cart_item::Model>, AppError> {
+    Ok(CartItem::find()
+        .filter(cart_item::Column::UserId.eq(user_id))
+        .order_by_asc(cart_item::Column::Id)
+        .all(db)
+        .await?)
+}
+
+/// Builds the cart response of a user.
+async fn user_cart_response(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Json<CartResponse>, AppError> {
+    let items = user_cart_items(db, user_id)
+        .await?
+        .into_iter()
+        .map(|item| (item.variant_id, item.quantity))
+        .collect();
+
+    Ok(Json(CartResponse {
+        status: true,
+        cart_token: None,
+        data: build_cart(db, items).await?,
+    }))
+}
+
+/// Builds the cart response of a guest.
+async fn guest_cart_response(
+    db: &DatabaseConnection,
+    token: Option<String>,
+    items: Vec<GuestCartItem>,
+) -> Result<Json<CartResponse>, AppError> {
+    let items = items
+        .into_iter()
+        .map(|item| (item.variant_id, item.quantity))
+        .collect();
+
+    Ok(Json(CartResponse {
+        status: true,
+        cart_token: token,
+        data: build_cart(db, items).await?,
+    }))
+}
+
+/// Shows the cart of the authenticated customer.
+pub async fn show(
+    State(db): State<DatabaseConnection>,
+    claims: AuthBearer,
+) -> Result<Json<CartResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+    user_cart_response(&db, user_id).await
+}
+
+/// Adds a variant to the cart of the authenticated customer.
+/// Adding a variant that is already in the cart increases its quantity.
+pub async fn add_item(
+    State(db): State<DatabaseConnection>,
+    claims: AuthBearer,
+    ValidatedJson(payload): ValidatedJson<AddCartItemRequest>,
+) -> Result<Json<CartResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+    let (variant, _) = find_purchasable_variant(&db, payload.variant_id).await?;
+
+    // The stock is checked against the quantity the upsert arrived at; the transaction is
+    // rolled back when there isn't enough.
+    let txn = db.begin().await?;
+    let cart_item_model = add_cart_item(&txn, user_id, variant.id, payload.quantity).await?;
+    ensure_in_stock(&variant, cart_item_model.quantity)?;
+    txn.commit().await?;
+
+    user_cart_response(&db, user_id).await
+}
+
+/// Changes the quantity of a variant in the cart of the authenticated customer.
+pub async fn update_item(
+    State(db): State<DatabaseConnection>,
+    claims: AuthBearer,
+    Path(variant_id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateCartItemRequest>,
+) -> Result<Json<CartResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+
+    let existing = CartItem::find()
+        .filter(cart_item::Column::UserId.eq(user_id))
+        .filter(cart_item::Column::VariantId.eq(variant_id))
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Item is not in the cart.".to_owned()))?;
+
+    let (variant, _) = find_purchasable_variant(&db, variant_id).await?;
+    ensure_in_stock(&variant, payload.quantity)?;
+
+    let mut cart_item_active_model: cart_item::ActiveModel = existing.into();
+    cart_item_active_model.quantity = Set(payload.quantity);
+    cart_item_active_model.updated_at = Set(Utc::now().naive_utc());
+    cart_item_active_model.update(&db).await?;
+
+    user_cart_response(&db, user_id).await
+}
+
+/// Removes a variant from the cart of the authenticated customer.
+pub async fn remove_item(
+    State(db): State<DatabaseConnection>,
+    claims: AuthBearer,
+    Path(variant_id): Path<i32>,
+) -> Result<Json<CartResponse>, AppError> {
+    let user_id = claims_user_id(&claims)?;
+
+    let existing = CartItem::find()
+        .filter(cart_item::Column::UserId.eq(user_id))
+        .filter(cart_item::Column::VariantId.eq(variant_id))
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Item is not in the cart.".to_owned()))?;
+    existing.delete(&db).await?;
+
+    user_cart_response(&db, user_id).await
+}
+
+/// Shows a guest cart.
+pub async fn guest_show(
+    State(state): State<AppState>,
+    GuestCartToken(token): GuestCartToken,
+) -> Result<Json<CartResponse>, AppError> {
+    let items = match &token {
+        Some(token) => load_guest_cart(&state.cache, token).await?,
+        None => Vec::new(),
+    };
+    guest_cart_response(&state.db, token, items).await
+}
+
+/// Adds a variant to a guest cart. A new cart (and token) is started when no token is sent.
+pub async fn guest_add_item(
+    State(state): State<AppState>,
+    GuestCartToken(token): GuestCartToken,
+    ValidatedJson(payload): ValidatedJson<AddCartItemRequest>,
+) -> Result<Json<CartResponse>, AppError> {
+    let (variant, _) = find_purchasable_variant(&state.db, payload.variant_id).await?;
+
+    let token = token.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut items = load_guest_cart(&state.cache, &token).await?;
+
+    match items.iter_mut().find(|item| item.variant_id == variant.id) {
+        Some(item) => {
+            let quantity = (item.quantity + payload.quantity).min(MAX_CART_QUANTITY);
+            ensure_in_stock(&variant, quantity)?;
+            item.quantity = quantity;
+        }
+        None => {
+            ensure_in_stock(&variant, payload.quantity)?;
+            items.push(GuestCartItem {
+                variant_id: variant.id,
+                quantity: payload.quantity,
+            });
+        }
+    }
+
+    save_guest_cart(&state.cache, &token, &items).await?;
+    guest_cart_response(&state.db, Some(token), items).await
+}
+
+/// Changes the quantity of a variant in a guest cart.
+pub async fn guest_update_item(
+    State(state): State<AppState>,
+    GuestCartToken(token): GuestCartToken,
+    Path(variant_id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateCartItemRequest>,
+) -> Result<Json<CartResponse>, AppError> {
+    let token = token.ok_or_else(|| AppError::NotFound("Item is not in the cart.".to_owned()))?;
+    let mut items = load_guest_cart(&state.cache, &token).await?;
+
+    let item = items
+        .iter_mut()
+        .find(|item| item.variant_id == variant_id)
+        .ok_or_else(|| AppError::NotFound("Item is not in the cart.".to_owned()))?;
+
+    let (variant, _) = find_purchasable_variant(&state.db, variant_id).await?;
+    ensure_in_stock(&variant, payload.quantity)?;
+    item.quantity = payload.quantity;
+
+    save_guest_cart(&state.cache, &token, &items).await?;
+    guest_cart_response(&state.db, Some(token), items).await
+}
+
+/// Removes a variant from a guest cart.
+pub async fn guest_remove_item(
+    State(state): State<AppState>,
+    GuestCartToken(token): GuestCartToken,
+    Path(variant_id): Path<i32>,
+) -> Result<Json<CartResponse>, AppError> {
+    let token = token.ok_or_else(|| AppError::NotFound("Item is not in the cart.".to_owned()))?;
+    let mut items = load_guest_cart(&state.cache, &token).await?;
+
+    let count = items.len();
+    items.retain(|item| item.variant_id != variant_id);
+    if items.len() == count {
+        return Err(AppError::NotFound("Item is not in the cart.".to_owned()));
+    }
+
+    save_guest_cart(&state.cache, &token, &items).await?;
+    guest_cart_response(&state.db, Some(token), items).await
+}
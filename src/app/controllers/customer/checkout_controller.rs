@@ -0,0 +1,200 @@
+use axum::{Json, extract::State};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::order_workflow::record_order_placed;
+use crate::config::rabbitmq::{EmailJob, QueuePublisher};
+use crate::errors::AppError;
+use crate::models::{
+    cart_item, cart_item::Entity as CartItem, order, order_item, product,
+    product::Entity as Product, product_variant, product_variant::Entity as ProductVariant, user,
+    user::Entity as User,
+};
+use crate::utils::helper::format_price;
+
+/// An order with its items.
+#[derive(Debug, Serialize)]
+pub struct OrderData {
+    #[serde(flatten)]
+    pub order: order::Model,
+    pub items: Vec<order_item::Model>,
+}
+
+/// A struct to represent a placed order.
+#[derive(Debug, Serialize)]
+pub struct OrderResponse {
+    pub status: bool,
+    pub message: String,
+    pub data: OrderData,
+}
+
+/// Queues the order confirmation email.
+async fn queue_order_confirmation(
+    queue: &QueuePublisher,
+    user_model: &user::Model,
+    data: &OrderData,
+) {
+    let rows: String = data
+        .items
+        .iter()
+        .map(|item| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                item.name,
+                item.quantity,
+                format_price(item.unit_price * i64::from(item.quantity))
+            )
+        })
+        .collect();
+
+    let email_task = EmailJob {
+        to: user_model.email.clone(),
+        subject: format!("Order #{} confirmation", data.order.id),
+        body: format!(
+            "<html><body><h1>Thank you for your order, {}!</h1><p>We received your order #{}.</p><table>{}</table><p>Total: {}</p></body></html>",
+            user_model.name,
+            data.order.id,
+            rows,
+            format_price(data.order.total)
+        ),
+    };
+
+    if let Err(e) = queue.publish_email(&email_task).await {
+        error!(
+            "Failed to queue confirmation email for order {}: {}",
+            data.order.id, e
+        );
+    }
+}
+
+/// Places an order for the items in the authenticated customer's cart.
+/// The order, its items, the stock reservation and emptying the cart happen in one transaction,
+/// so a variant that runs out of stock leaves everything untouched.
+pub async fn checkout(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+) -> Result<Json<OrderResponse>, AppError> {
+    let user_id: i32 = claims
+        .0
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))?;
+    let user_model = User::find_by_id(user_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found.".to_owned()))?;
+
+    let txn = state.db.begin().await?;
+    let now = Utc::now().naive_utc();
+
+    // Lock the cart rows, so a concurrent checkout of the same cart waits for this one and
+    // then finds the cart empty instead of ordering it a second time
+    let cart_items = CartItem::find()
+        .filter(cart_item::Column::UserId.eq(user_id))
+        .order_by_asc(cart_item::Column::Id)
+        .lock_exclusive()
+        .all(&txn)
+        .await?;
+    if cart_items.is_empty() {
+        return Err(AppError::BadRequest("Your cart is empty.".to_owned()));
+    }
+
+    let mut lines = Vec::with_capacity(cart_items.len());
+    for item in &cart_items {
+        let found = ProductVariant::find_by_id(item.variant_id)
+            .find_also_related(Product)
+            .one(&txn)
+            .await?;
+        let Some((variant, Some(product))) = found.filter(|(_, product)| {
+            product
+                .as_ref()
+                .is_some_and(|product| product.status == product::STATUS_ACTIVE)
+        }) else {
+            return Err(AppError::Conflict(
+                "An item in your cart is no longer available. Please review your cart.".to_owned(),
+            ));
+        };
+
+        // Reserve the stock; the condition makes concurrent checkouts unable to oversell
+        let reserved = ProductVariant::update_many()
+            .col_expr(
+                product_variant::Column::Stock,
+                Expr::col(product_variant::Column::Stock).sub(item.quantity),
+            )
+            .col_expr(product_variant::Column::UpdatedAt, Expr::value(now))
+            .filter(product_variant::Column::Id.eq(variant.id))
+            .filter(product_variant::Column::Stock.gte(item.quantity))
+            .exec(&txn)
+            .await?;
+        if reserved.rows_affected == 0 {
+            return Err(AppError::Conflict(format!(
+                "Not enough stock for {} ({}).",
+                product.name, variant.sku
+            )));
+        }
+
+        lines.push((variant, product, item.quantity));
+    }
+
+    let total = lines
+        .iter()
+        .map(|(variant, _, quantity)| variant.price * i64::from(*quantity))
+        .sum();
+    let order_model = order::ActiveModel {
+        user_id: Set(user_id),
+        status: Set(order::STATUS_PENDING.to_owned()),
+        total: Set(total),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    record_order_placed(&txn, &order_model).await?;
+
+    let mut items = Vec::with_capacity(lines.len());
+    for (variant, product, quantity) in lines {
+        let order_item_model = order_item::ActiveModel {
+            order_id: Set(order_model.id),
+            variant_id: Set(Some(variant.id)),
+            sku: Set(variant.sku),
+            name: Set(format!("{} - {}", product.name, variant.name)),
+            unit_price: Set(variant.price),
+            quantity: Set(quantity),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        items.push(order_item_model);
+    }
+
+    // Only the ordered items: one added in the meantime stays in the cart
+    CartItem::delete_many()
+        .filter(cart_item::Column::Id.is_in(cart_items.iter().map(|item| item.id)))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    info!("Order placed: {} by user {}", order_model.id, user_id);
+
+    let data = OrderData {
+        order: order_model,
+        items,
+    };
+    queue_order_confirmation(&state.queue, &user_model, &data).await;
+
+    Ok(Json(OrderResponse {
+        status: true,
+        message: "Order placed.".to_owned(),
+        data,
+    }))
+}
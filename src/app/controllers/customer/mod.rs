@@ -1 +1,6 @@
 pub mod auth_controller;
+pub mod cart_controller;
+pub mod checkout_controller;
+pub mod payment_controller;
+pub mod product_controller;
+pub mod session_controller;
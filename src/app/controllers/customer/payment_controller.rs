@@ -0,0 +1,49 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Serialize;
+
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::payments::start_payment;
+use crate::errors::AppError;
+use crate::models::{order, order::Entity as Order, payment};
+
+/// A struct to represent the payment of an order.
+#[derive(Debug, Serialize)]
+pub struct PaymentResponse {
+    pub status: bool,
+    pub message: String,
+    pub data: payment::Model,
+}
+
+/// Starts paying one of the authenticated customer's pending orders.
+/// Returns the payment intent to complete at the provider; asking again returns the same one.
+pub async fn pay(
+    State(state): State<AppState>,
+    claims: AuthBearer,
+    Path(id): Path<i32>,
+) -> Result<Json<PaymentResponse>, AppError> {
+    let user_id: i32 = claims
+        .0
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))?;
+
+    // Orders of other customers look like missing ones
+    Order::find_by_id(id)
+        .filter(order::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found.".to_owned()))?;
+
+    let payment_model = start_payment(&state, id).await?;
+
+    Ok(Json(PaymentResponse {
+        status: true,
+        message: "Payment started.".to_owned(),
+        data: payment_model,
+    }))
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeSet, HashMap};
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+};
+use serde::Deserialize;
+
+// The catalog shares the admin product types and query helpers.
+use crate::app::controllers::admin::product_controller::{
+    ProductData, ProductListResponse, ProductResponse, in_categories, load_product_data,
+    page_params,
+};
+use crate::errors::AppError;
+use crate::models::{category, category::Entity as Category, product, product::Entity as Product};
+
+/// The query parameters of the catalog.
+#[derive(Debug, Deserialize)]
+pub struct CatalogQuery {
+    /// Slug of a category; products of its subcategories are included.
+    pub category: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+/// Returns the id of the active category with the given slug and of all its active descendants.
+async fn category_tree_ids(db: &DatabaseConnection, slug: &str) -> Result<Vec<i32>, AppError> {
+    let categories = Category::find()
+        .filter(category::Column::Active.eq(true))
+        .all(db)
+        .await?;
+
+    let root = categories
+        .iter()
+        .find(|category| category.slug == slug)
+        .ok_or_else(|| AppError::NotFound("Category not found.".to_owned()))?;
+
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    for category in &categories {
+        if let Some(parent_id) = category.parent_id {
+            children.entry(parent_id).or_default().push(category.id);
+        }
+    }
+
+    let mut ids = BTreeSet::new();
+    let mut pending = vec![root.id];
+    while let Some(id) = pending.pop() {
+        if ids.insert(id) {
+            pending.extend(children.get(&id).into_iter().flatten());
+        }
+    }
+    Ok(ids.into_iter().collect())
+}
+
+/// Hides inactive categories from a catalog product.
+fn public_product_data(mut data: ProductData) -> ProductData {
+    data.categories.retain(|category| category.active);
+    data
+}
+
+/// Lists the active products, optionally limited to a category and its subcategories.
+pub async fn index(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<CatalogQuery>,
+) -> Result<Json<ProductListResponse>, AppError> {
+    let (page, per_page) = page_params(query.page, query.per_page);
+
+    let mut select = Product::find()
+        .filter(product::Column::Status.eq(product::STATUS_ACTIVE))
+        .order_by_asc(product::Column::Name);
+    if let Some(slug) = query.category {
+        let category_ids = category_tree_ids(&db, &slug).await?;
+        select = in_categories(select, category_ids);
+    }
+
+    let paginator = select.paginate(&db, per_page);
+    let total = paginator.num_items().await?;
+    let products = paginator.fetch_page(page - 1).await?;
+    let data = load_product_data(&db, products)
+        .await?
+        .into_iter()
+        .map(public_product_data)
+        .collect();
+
+    Ok(Json(ProductListResponse {
+        status: true,
+        data,
+        page,
+        per_page,
+        total,
+    }))
+}
+
+/// Shows an active product by its slug.
+pub async fn show(
+    State(db): State<DatabaseConnection>,
+    Path(slug): Path<String>,
+) -> Result<Json<ProductResponse>, AppError> {
+    let product_model = Product::find()
+        .filter(product::Column::Slug.eq(slug))
+        .filter(product::Column::Status.eq(product::STATUS_ACTIVE))
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found.".to_owned()))?;
+
+    let data = load_product_data(&db, vec![product_model]).await?.remove(0);
+
+    Ok(Json(ProductResponse {
+        status: true,
+        data: public_product_data(data),
+    }))
+}
@@ -0,0 +1,2 @@
+// Session management is the same for every role, so the customer tree shares the admin handlers.
+pub use crate::app::controllers::admin::session_controller::{destroy, index, logout_all};
@@ -1,3 +1,4 @@
-pub mod customer;
 pub mod admin;
-
+pub mod customer;
+pub mod payment_webhook_controller;
+pub mod well_known_controller;
@@ -0,0 +1,41 @@
+use axum::{Json, body::Bytes, extract::State, http::HeaderMap};
+use serde::Serialize;
+
+use crate::config::app_state::AppState;
+use crate::config::payment_provider::SIGNATURE_HEADER;
+use crate::config::payments::apply_webhook_event;
+use crate::errors::AppError;
+
+/// A struct to represent the acknowledgement of a webhook event.
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub status: bool,
+    pub message: String,
+}
+
+/// Receives payment events from the payment provider.
+/// The raw body is checked against the HMAC signature header before it is parsed.
+/// Redelivered events are acknowledged without being applied again.
+pub async fn handle(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<WebhookResponse>, AppError> {
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::BadRequest("Missing webhook signature.".to_owned()))?;
+
+    let event = state.payments.verify_webhook(signature, &body)?;
+    let applied = apply_webhook_event(&state, &event).await?;
+
+    let message = if applied {
+        "Event processed."
+    } else {
+        "Event already processed."
+    };
+    Ok(Json(WebhookResponse {
+        status: true,
+        message: message.to_owned(),
+    }))
+}
@@ -0,0 +1,10 @@
+use axum::{Json, extract::State};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::config::jwt;
+use crate::config::jwt_keys::JwtKeyStore;
+
+/// Publishes the public keys used to verify our JWTs (empty when signing with HS256).
+pub async fn jwks(State(keys): State<JwtKeyStore>) -> Json<JwkSet> {
+    Json(jwt::jwks(&keys))
+}
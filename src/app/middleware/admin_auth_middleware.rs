@@ -1,22 +1,22 @@
-use crate::config::blacklist::is_blacklisted;
-use crate::config::jwt::verify_jwt;
+use crate::config::app_state::AppState;
+use crate::config::blacklist::is_token_revoked;
+use crate::config::jwt::{TokenType, verify_jwt};
+use crate::errors::AppError;
 use anyhow::Result;
-use axum::{Json, extract::Request, http::StatusCode, middleware::Next, response::Response};
-use serde::Serialize;
-/// A custom error response for the authentication middleware.
-#[derive(Debug, Serialize)]
-pub struct AuthErrorResponse {
-    pub status: bool,
-    pub message: String,
-}
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
 
 /// Middleware to protect admin routes.
 /// It checks if the bearer token is valid and if the user's role is "Admin".
 #[allow(dead_code)]
 pub async fn admin_auth_middleware(
+    State(state): State<AppState>,
     req: Request,
     next: Next,
-) -> Result<Response, (StatusCode, Json<AuthErrorResponse>)> {
+) -> Result<Response, AppError> {
     let auth_header = req
         .headers()
         .get("Authorization")
@@ -26,51 +26,29 @@ pub async fn admin_auth_middleware(
         if header.starts_with("Bearer ") {
             header.trim_start_matches("Bearer ").to_string()
         } else {
-            let error_response = AuthErrorResponse {
-                status: false,
-                message: "Invalid token format. Bearer token expected.".to_owned(),
-            };
-            return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+            return Err(AppError::Unauthorized(
+                "Invalid token format. Bearer token expected.".to_owned(),
+            ));
         }
     } else {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Authorization header missing.".to_owned(),
-        };
-        return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+        return Err(AppError::Unauthorized(
+            "Authorization header missing.".to_owned(),
+        ));
     };
 
     // Verify the token and get the claims
-    let token_data = verify_jwt(&token_string).map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Invalid or expired token: {}", e),
-        };
-        (StatusCode::UNAUTHORIZED, Json(error_response))
-    })?;
+    let token_data = verify_jwt(&state.jwt_keys, &token_string, TokenType::Access)?;
 
-    // Check if the token is blacklisted after successful verification
-    if is_blacklisted(&token_string).await.map_err(|e| {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: format!("Failed to check token blacklist: {}", e),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })? {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "This token has been blacklisted.".to_owned(),
-        };
-        return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+    // Check if the token was logged out, its session was revoked or its token version is outdated
+    if is_token_revoked(&state.db, &state.cache, &token_data.claims).await? {
+        return Err(AppError::TokenRevoked);
     }
 
     // Check if the user's role is "Admin"
     if token_data.claims.role != "Admin" {
-        let error_response = AuthErrorResponse {
-            status: false,
-            message: "Access denied. Only administrators can view this page.".to_owned(),
-        };
-        return Err((StatusCode::FORBIDDEN, Json(error_response)));
+        return Err(AppError::Forbidden(
+            "Access denied. Only administrators can view this page.".to_owned(),
+        ));
     }
 
     Ok(next.run(req).await)
@@ -1,39 +1,41 @@
-use crate::config::blacklist::is_blacklisted;
-use crate::config::jwt::verify_jwt;
-use axum::{Json, extract::Request, http::StatusCode, middleware::Next, response::Response};
-use serde::Serialize;
-
-/// A custom error response for the authentication middleware.
-#[derive(Debug, Serialize)]
-pub struct AuthErrorResponse {
-    pub status: bool,
-    pub message: String,
-}
+use crate::config::app_state::AppState;
+use crate::config::blacklist::is_token_revoked;
+use crate::config::jwt::{TokenType, verify_jwt};
+use crate::errors::AppError;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
 
 /// Middleware to prevent authenticated users from accessing guest routes like login or register.
 pub async fn admin_guest_middleware(
+    State(state): State<AppState>,
     req: Request,
     next: Next,
-) -> Result<Response, (StatusCode, Json<AuthErrorResponse>)> {
+) -> Result<Response, AppError> {
     let auth_header = req
         .headers()
         .get("Authorization")
         .and_then(|header| header.to_str().ok());
 
-    if let Some(header) = auth_header {
-        if header.starts_with("Bearer ") {
-            let token_string = header.trim_start_matches("Bearer ").to_string();
+    if let Some(header) = auth_header
+        && header.starts_with("Bearer ")
+    {
+        let token_string = header.trim_start_matches("Bearer ").to_string();
 
-            let is_valid_token = verify_jwt(&token_string).is_ok();
-            let is_token_blacklisted = is_blacklisted(&token_string).await.unwrap_or(false);
+        // Check if the token is valid and not revoked
+        let is_active_token = match verify_jwt(&state.jwt_keys, &token_string, TokenType::Access) {
+            Ok(token_data) => !is_token_revoked(&state.db, &state.cache, &token_data.claims)
+                .await
+                .unwrap_or(false),
+            Err(_) => false,
+        };
 
-            if is_valid_token && !is_token_blacklisted {
-                let error_response = AuthErrorResponse {
-                    status: false,
-                    message: "Access denied. You are already logged in.".to_owned(),
-                };
-                return Err((StatusCode::FORBIDDEN, Json(error_response)));
-            }
+        if is_active_token {
+            return Err(AppError::Forbidden(
+                "Access denied. You are already logged in.".to_owned(),
+            ));
         }
     }
 
@@ -1 +1,56 @@
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::errors::AppError;
+use crate::models::user::Entity as User;
+use axum::{
+    extract::{FromRequestParts, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use sea_orm::EntityTrait;
 
+/// How long a positive verification result is cached (seconds).
+const VERIFIED_CACHE_TTL: usize = 86400;
+
+/// Redis key holding the cached verification flag of a user.
+pub fn email_verified_cache_key(user_id: &str) -> String {
+    format!("email_verified:{}", user_id)
+}
+
+/// Middleware to make sure the authenticated user has verified their email address.
+/// It must be layered inside an auth middleware (admin or customer), which validates the token first.
+pub async fn email_verified_middleware(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (mut parts, body) = req.into_parts();
+
+    let AuthBearer(claims) = AuthBearer::from_request_parts(&mut parts, &state).await?;
+    let cache_key = email_verified_cache_key(&claims.id);
+
+    // Only verified users are cached, so a fresh verification takes effect immediately.
+    if let Ok(Some(_)) = state.cache.get(&cache_key).await {
+        return Ok(next.run(Request::from_parts(parts, body)).await);
+    }
+
+    let user_id: i32 = claims
+        .id
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token.".to_owned()))?;
+
+    let user_model = User::find_by_id(user_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found.".to_owned()))?;
+
+    if user_model.email_verified_at.is_none() {
+        return Err(AppError::EmailNotVerified);
+    }
+
+    // A cache failure should not block a verified user.
+    let _ = state.cache.set(&cache_key, "1", VERIFIED_CACHE_TTL).await;
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
@@ -3,3 +3,5 @@ pub mod admin_guest_middleware;
 pub mod customer_auth_middleware;
 pub mod customer_guest_middleware;
 pub mod email_verified_middleware;
+pub mod rate_limit_middleware;
+pub mod two_factor_middleware;
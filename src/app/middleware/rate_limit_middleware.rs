@@ -0,0 +1,165 @@
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::client_info::ClientInfo;
+use crate::config::rate_limiter::{RateLimitDecision, RateLimitStrategy};
+use crate::errors::AppError;
+use axum::{
+    extract::{FromRequestParts, MatchedPath, State},
+    http::{HeaderMap, HeaderValue, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::error;
+
+/// What a rate limit is counted per.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// Per client IP address.
+    Ip,
+    /// Per authenticated user (from the bearer token), falling back to the IP address.
+    User,
+    /// One shared limit for the route, whoever calls it.
+    Route,
+}
+
+/// A rate limit for one route (or group of routes). Paired with the app state, it is the state
+/// of `rate_limit_middleware`.
+///
+/// ```text
+/// post(handler).layer(from_fn_with_state(
+///     (state.clone(), RateLimit::per_ip("login", 10, 60).sliding_window()),
+///     rate_limit_middleware::rate_limit_middleware,
+/// ))
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub name: &'static str,
+    pub limit: u64,
+    pub window_seconds: u64,
+    pub key: RateLimitKey,
+    pub strategy: RateLimitStrategy,
+}
+
+impl RateLimit {
+    /// `limit` requests per `window_seconds` per IP address, counted in fixed windows.
+    pub fn per_ip(name: &'static str, limit: u64, window_seconds: u64) -> Self {
+        Self {
+            name,
+            limit,
+            window_seconds,
+            key: RateLimitKey::Ip,
+            strategy: RateLimitStrategy::FixedWindow,
+        }
+    }
+
+    /// `limit` requests per `window_seconds` per authenticated user, counted in fixed windows.
+    pub fn per_user(name: &'static str, limit: u64, window_seconds: u64) -> Self {
+        Self {
+            key: RateLimitKey::User,
+            ..Self::per_ip(name, limit, window_seconds)
+        }
+    }
+
+    /// `limit` requests per `window_seconds` for the route as a whole, counted in fixed windows.
+    pub fn per_route(name: &'static str, limit: u64, window_seconds: u64) -> Self {
+        Self {
+            key: RateLimitKey::Route,
+            ..Self::per_ip(name, limit, window_seconds)
+        }
+    }
+
+    /// Counts requests in a sliding window instead.
+    pub fn sliding_window(self) -> Self {
+        Self {
+            strategy: RateLimitStrategy::SlidingWindow,
+            ..self
+        }
+    }
+
+    /// Uses a token bucket instead.
+    pub fn token_bucket(self) -> Self {
+        Self {
+            strategy: RateLimitStrategy::TokenBucket,
+            ..self
+        }
+    }
+}
+
+/// Adds the `X-RateLimit-*` headers (and `Retry-After` when rejected) to a response.
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert("X-RateLimit-Limit", HeaderValue::from(decision.limit));
+    headers.insert(
+        "X-RateLimit-Remaining",
+        HeaderValue::from(decision.remaining),
+    );
+    headers.insert("X-RateLimit-Reset", HeaderValue::from(decision.reset_after));
+    if !decision.allowed {
+        headers.insert("Retry-After", HeaderValue::from(decision.retry_after));
+    }
+}
+
+/// Middleware that throttles requests according to its `RateLimit` state.
+/// Rejected requests get a 429 response. If the cache is unavailable the request is let through.
+pub async fn rate_limit_middleware(
+    State((state, rate_limit)): State<(AppState, RateLimit)>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = req.into_parts();
+
+    let identity = match rate_limit.key {
+        RateLimitKey::Route => parts
+            .extensions
+            .get::<MatchedPath>()
+            .map(|path| format!("{}:{}", parts.method, path.as_str()))
+            .unwrap_or_else(|| format!("{}:{}", parts.method, parts.uri.path())),
+        RateLimitKey::User => match AuthBearer::from_request_parts(&mut parts, &state).await {
+            Ok(AuthBearer(claims)) => format!("user:{}", claims.id),
+            Err(_) => client_ip(&mut parts, &state).await,
+        },
+        RateLimitKey::Ip => client_ip(&mut parts, &state).await,
+    };
+    let key = format!("rate_limit:{}:{}", rate_limit.name, identity);
+
+    let decision = match state
+        .cache
+        .check_rate_limit(
+            &key,
+            rate_limit.limit,
+            rate_limit.window_seconds,
+            rate_limit.strategy,
+        )
+        .await
+    {
+        Ok(decision) => decision,
+        Err(e) => {
+            error!("Rate limit check failed for {}: {}", key, e);
+            return next.run(Request::from_parts(parts, body)).await;
+        }
+    };
+
+    let mut response = if decision.allowed {
+        next.run(Request::from_parts(parts, body)).await
+    } else {
+        AppError::TooManyRequests {
+            message: format!(
+                "Too many requests. Please try again in {} seconds.",
+                decision.retry_after
+            ),
+            retry_after: Some(decision.retry_after),
+        }
+        .into_response()
+    };
+
+    insert_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+/// The caller's IP address, as used to label sessions.
+async fn client_ip(parts: &mut axum::http::request::Parts, state: &AppState) -> String {
+    let client = match ClientInfo::from_request_parts(parts, state).await {
+        Ok(client) => client,
+        Err(infallible) => match infallible {},
+    };
+    format!("ip:{}", client.ip_address.as_deref().unwrap_or("unknown"))
+}
@@ -0,0 +1,29 @@
+use crate::config::app_state::AppState;
+use crate::config::auth_bearer::AuthBearer;
+use crate::config::two_factor::two_factor_required_for;
+use crate::errors::AppError;
+use axum::{
+    extract::{FromRequestParts, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+
+/// Middleware that enforces two-factor authentication where it is required for the user's role.
+/// Tokens from a login without a second factor are rejected until the user enrols and logs in again.
+/// It must be layered inside an auth middleware, which validates the token first.
+pub async fn two_factor_middleware(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (mut parts, body) = req.into_parts();
+
+    let AuthBearer(claims) = AuthBearer::from_request_parts(&mut parts, &state).await?;
+
+    if two_factor_required_for(&state.config, &claims.role) && !claims.mfa {
+        return Err(AppError::TwoFactorRequired);
+    }
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
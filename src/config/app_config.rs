@@ -0,0 +1,419 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::two_factor::encryption_key;
+
+/// The TOML file read when `CONFIG_FILE` is not set; it is optional.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Where cached values (revoked tokens, rate limits, ...) are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    /// Redis/KeyDB at `REDIS_URL`; shared by every app instance.
+    Redis,
+    /// In-process memory; for tests and single-node dev setups.
+    Memory,
+}
+
+impl FromStr for CacheBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "redis" => Ok(Self::Redis),
+            "memory" => Ok(Self::Memory),
+            other => Err(anyhow!("unsupported cache backend {}", other)),
+        }
+    }
+}
+
+/// A setting that must not end up in logs, such as a password or a signing key.
+/// `Debug` prints `[redacted]`; read the value with `expose`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Infallible> {
+        Ok(Self::from(value))
+    }
+}
+
+/// Settings read once at startup.
+///
+/// Every setting has a default, which can be overridden by the TOML file at `CONFIG_FILE`
+/// (default `config.toml`) and then by the environment variable of the same name in upper
+/// case (e.g. `access_token_ttl` and `ACCESS_TOKEN_TTL`). See `config.example.toml`.
+/// Durations are in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub port: u16,
+    /// Public base URL of the app, used for the links in emails.
+    pub app_url: String,
+    /// Frontend page that password reset emails link to, with the token in the `token`
+    /// query parameter. The page posts the token and the new password to `reset-password`.
+    pub password_reset_url: String,
+    /// Addresses of the reverse proxies whose `X-Forwarded-For` / `X-Real-IP` headers are
+    /// trusted. Any other peer is taken to be the client itself.
+    pub trusted_proxies: Vec<IpAddr>,
+    pub database_url: Secret,
+
+    /// `redis` (default) or `memory`.
+    pub cache_backend: CacheBackend,
+    /// Only needed by the Redis cache backend.
+    pub redis_url: Option<Secret>,
+
+    /// `HS256` (default, signs with `jwt_secret`), `RS256` or `EdDSA`.
+    pub jwt_algorithm: String,
+    pub jwt_secret: Option<Secret>,
+    /// Directory with `<kid>.pub.pem` / `<kid>.key.pem` files for `RS256` and `EdDSA`.
+    pub jwt_keys_dir: String,
+    /// Key id used to sign new tokens with `RS256` and `EdDSA`.
+    pub jwt_active_kid: Option<String>,
+    pub access_token_ttl: usize,
+    pub refresh_token_ttl: usize,
+    pub email_verification_ttl: usize,
+    pub password_reset_ttl: usize,
+
+    /// Whether admins must log in with a second factor.
+    pub admin_require_2fa: bool,
+    /// Base64 encoded 32 byte key that encrypts TOTP secrets; needed to enrol a second factor.
+    pub totp_encryption_key: Option<Secret>,
+
+    /// Only `mock` for now.
+    pub payment_provider: String,
+    /// The secret payment webhooks are signed with.
+    pub payment_webhook_secret: Secret,
+    /// Currency of new payments.
+    pub payment_currency: String,
+
+    /// SMTP server; emails are only sent when it is set.
+    pub mail_host: Option<String>,
+    pub mail_port: u16,
+    pub mail_username: Option<String>,
+    pub mail_password: Option<Secret>,
+    pub mail_from: Option<String>,
+
+    pub rabbitmq_host: String,
+    pub rabbitmq_port: u16,
+    pub rabbitmq_user: String,
+    pub rabbitmq_pass: Secret,
+    /// Queue the emails are published to and consumed from by the email worker.
+    pub email_queue: String,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            app_url: "http://localhost:8080".to_owned(),
+            password_reset_url: "http://localhost:3000/reset-password".to_owned(),
+            trusted_proxies: Vec::new(),
+            database_url: Secret::default(),
+            cache_backend: CacheBackend::Redis,
+            redis_url: None,
+            jwt_algorithm: "HS256".to_owned(),
+            jwt_secret: None,
+            jwt_keys_dir: "keys".to_owned(),
+            jwt_active_kid: None,
+            access_token_ttl: 3600,
+            refresh_token_ttl: 2592000,
+            email_verification_ttl: 86400,
+            password_reset_ttl: 3600,
+            admin_require_2fa: false,
+            totp_encryption_key: None,
+            payment_provider: "mock".to_owned(),
+            payment_webhook_secret: Secret::default(),
+            payment_currency: "usd".to_owned(),
+            mail_host: None,
+            mail_port: 587,
+            mail_username: None,
+            mail_password: None,
+            mail_from: None,
+            rabbitmq_host: "rabbitmq".to_owned(),
+            rabbitmq_port: 5672,
+            rabbitmq_user: "admin".to_owned(),
+            rabbitmq_pass: Secret::default(),
+            email_queue: "email_queue".to_owned(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Loads the settings (defaults, then the TOML file, then the environment) and
+    /// validates them.
+    pub fn load() -> Result<Self> {
+        let config = Self::read()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Like `load`, but only validates what the email worker uses: RabbitMQ and mail.
+    pub fn load_for_worker() -> Result<Self> {
+        let config = Self::read()?;
+        config.validate_for_worker()?;
+        Ok(config)
+    }
+
+    /// Reads the settings without validating them.
+    fn read() -> Result<Self> {
+        let config = match env::var("CONFIG_FILE") {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            Err(_) => Self::default(),
+        };
+        config.with_env()
+    }
+
+    /// Reads a TOML file; settings it leaves out keep their defaults.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    /// Overrides the settings with the environment variables that are set.
+    pub fn with_env(mut self) -> Result<Self> {
+        override_from_env("PORT", &mut self.port)?;
+        override_from_env("APP_URL", &mut self.app_url)?;
+        override_from_env("PASSWORD_RESET_URL", &mut self.password_reset_url)?;
+        override_list_from_env("TRUSTED_PROXIES", &mut self.trusted_proxies)?;
+        override_from_env("DATABASE_URL", &mut self.database_url)?;
+        override_from_env("CACHE_BACKEND", &mut self.cache_backend)?;
+        override_optional_from_env("REDIS_URL", &mut self.redis_url)?;
+        override_from_env("JWT_ALGORITHM", &mut self.jwt_algorithm)?;
+        override_optional_from_env("JWT_SECRET", &mut self.jwt_secret)?;
+        override_from_env("JWT_KEYS_DIR", &mut self.jwt_keys_dir)?;
+        override_optional_from_env("JWT_ACTIVE_KID", &mut self.jwt_active_kid)?;
+        override_from_env("ACCESS_TOKEN_TTL", &mut self.access_token_ttl)?;
+        override_from_env("REFRESH_TOKEN_TTL", &mut self.refresh_token_ttl)?;
+        override_from_env("EMAIL_VERIFICATION_TTL", &mut self.email_verification_ttl)?;
+        override_from_env("PASSWORD_RESET_TTL", &mut self.password_reset_ttl)?;
+        if let Ok(value) = env::var("ADMIN_REQUIRE_2FA") {
+            self.admin_require_2fa = value.eq_ignore_ascii_case("true") || value == "1";
+        }
+        override_optional_from_env("TOTP_ENCRYPTION_KEY", &mut self.totp_encryption_key)?;
+        override_from_env("PAYMENT_PROVIDER", &mut self.payment_provider)?;
+        override_from_env("PAYMENT_WEBHOOK_SECRET", &mut self.payment_webhook_secret)?;
+        override_from_env("PAYMENT_CURRENCY", &mut self.payment_currency)?;
+        override_optional_from_env("MAIL_HOST", &mut self.mail_host)?;
+        override_from_env("MAIL_PORT", &mut self.mail_port)?;
+        override_optional_from_env("MAIL_USERNAME", &mut self.mail_username)?;
+        override_optional_from_env("MAIL_PASSWORD", &mut self.mail_password)?;
+        override_optional_from_env("MAIL_FROM", &mut self.mail_from)?;
+        override_from_env("RABBITMQ_HOST", &mut self.rabbitmq_host)?;
+        override_from_env("RABBITMQ_PORT", &mut self.rabbitmq_port)?;
+        override_from_env("RABBITMQ_USER", &mut self.rabbitmq_user)?;
+        override_from_env("RABBITMQ_PASS", &mut self.rabbitmq_pass)?;
+        override_from_env("EMAIL_QUEUE", &mut self.email_queue)?;
+
+        self.app_url = self.app_url.trim_end_matches('/').to_owned();
+        Ok(self)
+    }
+
+    /// Checks the settings, reporting every problem at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if !self.app_url.starts_with("http://") && !self.app_url.starts_with("https://") {
+            problems.push("APP_URL must start with http:// or https://".to_owned());
+        }
+        if !self.password_reset_url.starts_with("http://")
+            && !self.password_reset_url.starts_with("https://")
+        {
+            problems.push("PASSWORD_RESET_URL must start with http:// or https://".to_owned());
+        }
+        if self.database_url.expose().is_empty() {
+            problems.push("DATABASE_URL must be set".to_owned());
+        }
+        if self.cache_backend == CacheBackend::Redis && self.redis_url.is_none() {
+            problems.push("REDIS_URL must be set for the redis cache backend".to_owned());
+        }
+
+        match self.jwt_algorithm.as_str() {
+            "HS256" if self.jwt_secret.is_none() => {
+                problems.push("JWT_SECRET must be set for HS256".to_owned());
+            }
+            "RS256" | "EdDSA" if self.jwt_active_kid.is_none() => {
+                problems.push(format!(
+                    "JWT_ACTIVE_KID must be set for {}",
+                    self.jwt_algorithm
+                ));
+            }
+            "HS256" | "RS256" | "EdDSA" => {}
+            other => problems.push(format!("Unsupported JWT_ALGORITHM: {}", other)),
+        }
+        for (name, ttl) in [
+            ("ACCESS_TOKEN_TTL", self.access_token_ttl),
+            ("REFRESH_TOKEN_TTL", self.refresh_token_ttl),
+            ("EMAIL_VERIFICATION_TTL", self.email_verification_ttl),
+            ("PASSWORD_RESET_TTL", self.password_reset_ttl),
+        ] {
+            if ttl == 0 {
+                problems.push(format!("{} must be greater than 0", name));
+            }
+        }
+        if self.refresh_token_ttl <= self.access_token_ttl {
+            problems.push("REFRESH_TOKEN_TTL must be greater than ACCESS_TOKEN_TTL".to_owned());
+        }
+
+        if self.totp_encryption_key.is_some()
+            && let Err(e) = encryption_key(self)
+        {
+            problems.push(e.to_string());
+        }
+
+        if self.payment_provider != "mock" {
+            problems.push(format!(
+                "Unsupported PAYMENT_PROVIDER: {}",
+                self.payment_provider
+            ));
+        }
+        if self.payment_webhook_secret.expose().is_empty() {
+            problems.push("PAYMENT_WEBHOOK_SECRET must be set".to_owned());
+        }
+        if self.payment_currency.is_empty() {
+            problems.push("PAYMENT_CURRENCY must not be empty".to_owned());
+        }
+
+        self.check_mail_and_queue(&mut problems);
+        report(problems)
+    }
+
+    /// Checks the settings the email worker uses. Unlike the app, it can't run without
+    /// an SMTP server.
+    pub fn validate_for_worker(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if self.mail_host.is_none() {
+            problems.push("MAIL_HOST must be set".to_owned());
+        }
+        self.check_mail_and_queue(&mut problems);
+        report(problems)
+    }
+
+    /// The checks shared by the app and the email worker.
+    fn check_mail_and_queue(&self, problems: &mut Vec<String>) {
+        if self.mail_host.is_some() {
+            for (name, is_set) in [
+                ("MAIL_USERNAME", self.mail_username.is_some()),
+                ("MAIL_PASSWORD", self.mail_password.is_some()),
+                ("MAIL_FROM", self.mail_from.is_some()),
+            ] {
+                if !is_set {
+                    problems.push(format!("{} must be set when MAIL_HOST is set", name));
+                }
+            }
+        }
+        if self.rabbitmq_pass.expose().is_empty() {
+            problems.push("RABBITMQ_PASS must be set".to_owned());
+        }
+        if self.email_queue.is_empty() {
+            problems.push("EMAIL_QUEUE must not be empty".to_owned());
+        }
+    }
+
+    /// The RabbitMQ URL, on the default vhost.
+    pub fn rabbitmq_url(&self) -> String {
+        format!(
+            "amqp://{}:{}@{}:{}/%2f",
+            self.rabbitmq_user,
+            self.rabbitmq_pass.expose(),
+            self.rabbitmq_host,
+            self.rabbitmq_port
+        )
+    }
+}
+
+/// Fails with every problem found, if there are any.
+fn report(problems: Vec<String>) -> Result<()> {
+    if !problems.is_empty() {
+        bail!("Invalid configuration: {}", problems.join("; "));
+    }
+    Ok(())
+}
+
+/// Replaces a setting with the value of an environment variable, if it is set.
+fn override_from_env<T>(name: &str, setting: &mut T) -> Result<()>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Ok(value) = env::var(name) {
+        *setting = value
+            .parse()
+            .map_err(|e| anyhow!("Failed to parse {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// Like `override_from_env`, for a comma separated list. An empty variable clears it.
+fn override_list_from_env<T>(name: &str, setting: &mut Vec<T>) -> Result<()>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Ok(value) = env::var(name) {
+        *setting = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                item.parse()
+                    .map_err(|e| anyhow!("Failed to parse {}: {}", name, e))
+            })
+            .collect::<Result<_>>()?;
+    }
+    Ok(())
+}
+
+/// Like `override_from_env`, for a setting that may be missing. An empty variable unsets it.
+fn override_optional_from_env<T>(name: &str, setting: &mut Option<T>) -> Result<()>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Ok(value) = env::var(name) {
+        *setting = if value.is_empty() {
+            None
+        } else {
+            Some(
+                value
+                    .parse()
+                    .map_err(|e| anyhow!("Failed to parse {}: {}", name, e))?,
+            )
+        };
+    }
+    Ok(())
+}
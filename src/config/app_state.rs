@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::extract::FromRef;
+use sea_orm::DatabaseConnection;
+use tracing::warn;
+
+use super::app_config::AppConfig;
+use super::cache::{SharedCache, cache_from_config};
+use super::database::connect_db;
+use super::jwt_keys::JwtKeyStore;
+use super::mail::EmailSender;
+use super::payment_provider::{PaymentProvider, provider_from_config};
+use super::rabbitmq::QueuePublisher;
+
+/// Everything handlers, middlewares and extractors depend on, passed to the router with
+/// `Router::with_state`. Cloning is cheap; every field is a shared handle.
+///
+/// Handlers take the whole state with `State(state): State<AppState>`, or a single part such
+/// as `State(db): State<DatabaseConnection>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub db: DatabaseConnection,
+    pub cache: SharedCache,
+    pub jwt_keys: JwtKeyStore,
+    pub queue: QueuePublisher,
+    /// SMTP client; `None` when `MAIL_*` is not configured. Emails are normally sent by the
+    /// worker from the queue.
+    pub mailer: Option<Arc<EmailSender>>,
+    pub payments: Arc<dyn PaymentProvider>,
+    pub config: Arc<AppConfig>,
+}
+
+impl AppState {
+    /// Connects to the database and cache backend and loads the keys and providers for a config.
+    pub async fn new(config: AppConfig) -> Result<Self> {
+        let config = Arc::new(config);
+        let db = connect_db(config.database_url.expose()).await?;
+        let cache = cache_from_config(&config).await?;
+        let jwt_keys = JwtKeyStore::load(config.clone())?;
+        let payments = provider_from_config(&config)?;
+
+        let mailer = match EmailSender::new(&config) {
+            Ok(mailer) => Some(Arc::new(mailer)),
+            Err(e) => {
+                warn!("Mailer is not configured: {}", e);
+                None
+            }
+        };
+
+        Ok(Self {
+            db,
+            cache,
+            jwt_keys,
+            queue: QueuePublisher::new(&config),
+            mailer,
+            payments,
+            config,
+        })
+    }
+}
+
+impl FromRef<AppState> for DatabaseConnection {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedCache {
+    fn from_ref(state: &AppState) -> Self {
+        state.cache.clone()
+    }
+}
+
+impl FromRef<AppState> for JwtKeyStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.jwt_keys.clone()
+    }
+}
+
+impl FromRef<AppState> for QueuePublisher {
+    fn from_ref(state: &AppState) -> Self {
+        state.queue.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn PaymentProvider> {
+    fn from_ref(state: &AppState) -> Self {
+        state.payments.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AppConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
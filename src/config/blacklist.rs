@@ -1,21 +1,50 @@
-use super::redis::{delete_key, get_value, set_value};
+use super::cache::{Cache, CacheResult};
+use super::jwt::JwtClaims;
+use super::sessions::session_revocation_key;
+use super::token_version::current_token_version;
+use anyhow::Result;
 use chrono::Utc;
+use sea_orm::DatabaseConnection;
 
-#[allow(dead_code)]
-pub async fn blacklist_token(token: &str, exp: usize) -> redis::RedisResult<()> {
+/// Cache key marking a single token as revoked, by its `jti` claim.
+pub fn revoked_jti_key(jti: &str) -> String {
+    format!("revoked:jti:{}", jti)
+}
+
+/// Revokes a single token by its `jti` until it expires on its own (`exp`).
+pub async fn blacklist_token(cache: &dyn Cache, jti: &str, exp: usize) -> CacheResult<()> {
     let ttl = exp as i64 - Utc::now().timestamp();
     if ttl > 0 {
-        set_value(token, "blacklisted", ttl as usize).await?;
+        cache
+            .set(&revoked_jti_key(jti), "revoked", ttl as usize)
+            .await?;
     }
     Ok(())
 }
 
-#[allow(dead_code)]
-pub async fn is_blacklisted(token: &str) -> redis::RedisResult<bool> {
-    Ok(get_value(token).await?.is_some())
+/// Checks whether the token with the given `jti` was revoked.
+pub async fn is_blacklisted(cache: &dyn Cache, jti: &str) -> CacheResult<bool> {
+    cache.exists(&revoked_jti_key(jti)).await
 }
 
-#[allow(dead_code)]
-pub async fn remove_from_blacklist(token: &str) -> redis::RedisResult<()> {
-    delete_key(token).await
+/// Checks whether a login token was revoked: on its own (logout), by ending its session
+/// or by a bump of the user's token version.
+/// This is the one check every middleware and the token refresh use.
+pub async fn is_token_revoked(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    claims: &JwtClaims,
+) -> Result<bool> {
+    if is_blacklisted(cache, &claims.jti).await? {
+        return Ok(true);
+    }
+
+    if cache.exists(&session_revocation_key(&claims.sid)).await? {
+        return Ok(true);
+    }
+
+    let Ok(user_id) = claims.id.parse::<i32>() else {
+        return Ok(true);
+    };
+    Ok(current_token_version(db, cache, user_id).await? != Some(claims.ver))
 }
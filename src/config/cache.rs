@@ -0,0 +1,187 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use axum::async_trait;
+use redis::RedisError;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use tracing::error;
+
+use super::app_config::{AppConfig, CacheBackend};
+use super::memory_cache::MemoryCache;
+use super::rate_limiter::{RateLimitDecision, RateLimitStrategy};
+use super::redis::RedisCache;
+
+/// Errors raised by a cache backend.
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] RedisError),
+
+    #[error("Cache command timed out")]
+    Timeout,
+
+    #[error("Invalid cached value: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    WrongType(String),
+}
+
+pub type CacheResult<T> = Result<T, CacheError>;
+
+/// The cache handle kept in the app state.
+pub type SharedCache = Arc<dyn Cache>;
+
+/// A key-value cache with per-key expiry.
+///
+/// Values are strings; use the `CacheExt` helpers to store typed values as JSON. TTLs are
+/// in seconds. Implemented by `RedisCache` (shared between app instances) and `MemoryCache`
+/// (one process, for tests and single-node dev setups).
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// Returns the value of a key, or `None` when it is missing or expired.
+    async fn get(&self, key: &str) -> CacheResult<Option<String>>;
+
+    /// Sets a key that expires after `ttl_seconds`.
+    async fn set(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<()>;
+
+    /// Sets a key that expires after `ttl_seconds`, unless it already exists. Returns whether
+    /// the key was set; the check and the write are atomic.
+    async fn set_if_absent(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<bool>;
+
+    /// Deletes a key; a missing key is not an error.
+    async fn delete(&self, key: &str) -> CacheResult<()>;
+
+    /// Returns whether a key exists.
+    async fn exists(&self, key: &str) -> CacheResult<bool>;
+
+    /// Increments a counter and starts its TTL window (seconds) on the first hit.
+    async fn incr(&self, key: &str, ttl_seconds: usize) -> CacheResult<i64>;
+
+    /// Remaining time to live of a key in seconds (negative if the key is missing or has no TTL).
+    async fn ttl(&self, key: &str) -> CacheResult<i64>;
+
+    /// Counts a request against `key` and decides whether it is within `limit` requests
+    /// per `window_seconds`. The check and the update are atomic.
+    async fn check_rate_limit(
+        &self,
+        key: &str,
+        limit: u64,
+        window_seconds: u64,
+        strategy: RateLimitStrategy,
+    ) -> CacheResult<RateLimitDecision>;
+}
+
+/// Lets the shared handle be passed wherever a `&dyn Cache` is expected.
+#[async_trait]
+impl<C: Cache + ?Sized> Cache for Arc<C> {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        (**self).get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<()> {
+        (**self).set(key, value, ttl_seconds).await
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<bool> {
+        (**self).set_if_absent(key, value, ttl_seconds).await
+    }
+
+    async fn delete(&self, key: &str) -> CacheResult<()> {
+        (**self).delete(key).await
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        (**self).exists(key).await
+    }
+
+    async fn incr(&self, key: &str, ttl_seconds: usize) -> CacheResult<i64> {
+        (**self).incr(key, ttl_seconds).await
+    }
+
+    async fn ttl(&self, key: &str) -> CacheResult<i64> {
+        (**self).ttl(key).await
+    }
+
+    async fn check_rate_limit(
+        &self,
+        key: &str,
+        limit: u64,
+        window_seconds: u64,
+        strategy: RateLimitStrategy,
+    ) -> CacheResult<RateLimitDecision> {
+        (**self)
+            .check_rate_limit(key, limit, window_seconds, strategy)
+            .await
+    }
+}
+
+/// Typed helpers on top of any `Cache`.
+#[async_trait]
+pub trait CacheExt: Cache {
+    /// Returns the JSON value of a key, or `None` when it is missing or expired.
+    async fn get_json<T: DeserializeOwned>(&self, key: &str) -> CacheResult<Option<T>> {
+        match self.get(key).await? {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a value as JSON for `ttl_seconds`.
+    async fn set_json<T: Serialize + Sync>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: usize,
+    ) -> CacheResult<()> {
+        let value = serde_json::to_string(value)?;
+        self.set(key, &value, ttl_seconds).await
+    }
+
+    /// Returns the cached JSON value of a key, or loads it with `load` and caches it for
+    /// `ttl_seconds`. Cache failures are logged and fall back to `load`, so the cache only
+    /// ever speeds a request up; errors of `load` are returned. A cached value that no longer
+    /// deserializes is treated as a miss.
+    async fn get_or_set_json<T, F, Fut, E>(
+        &self,
+        key: &str,
+        ttl_seconds: usize,
+        load: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned + Send + Sync,
+        F: FnOnce() -> Fut + Send,
+        Fut: Future<Output = Result<T, E>> + Send,
+    {
+        match self.get_json(key).await {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) => error!("Failed to read {} from the cache: {}", key, e),
+        }
+
+        let value = load().await?;
+        if let Err(e) = self.set_json(key, &value, ttl_seconds).await {
+            error!("Failed to cache {}: {}", key, e);
+        }
+        Ok(value)
+    }
+}
+
+impl<C: Cache + ?Sized> CacheExt for C {}
+
+/// Builds the cache backend selected by `CACHE_BACKEND`.
+pub async fn cache_from_config(config: &AppConfig) -> Result<SharedCache> {
+    match config.cache_backend {
+        CacheBackend::Redis => {
+            let redis_url = config
+                .redis_url
+                .as_ref()
+                .ok_or_else(|| anyhow!("REDIS_URL must be set for the redis cache backend"))?;
+            Ok(Arc::new(RedisCache::connect(redis_url.expose()).await?))
+        }
+        CacheBackend::Memory => Ok(Arc::new(MemoryCache::new())),
+    }
+}
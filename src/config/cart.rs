@@ -0,0 +1,146 @@
+use anyhow::Result;
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::Utc;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, Set};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::cache::{Cache, CacheExt};
+use crate::errors::AppError;
+use crate::models::{
+    cart_item, cart_item::Entity as CartItem, product_variant::Entity as ProductVariant,
+};
+
+/// Header carrying the token of a guest cart.
+pub const GUEST_CART_HEADER: &str = "X-Cart-Token";
+/// Lifetime of a guest cart (seconds), renewed on every change.
+const GUEST_CART_TTL: usize = 7 * 86400;
+/// Largest quantity of a single variant in a cart.
+pub const MAX_CART_QUANTITY: i32 = 100;
+
+/// One line of a guest cart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestCartItem {
+    pub variant_id: i32,
+    pub quantity: i32,
+}
+
+/// A custom extractor for the optional guest cart token header.
+/// The token is a UUID handed out when the first item is added to a guest cart.
+#[derive(Debug, Clone, Default)]
+pub struct GuestCartToken(pub Option<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for GuestCartToken
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(GUEST_CART_HEADER) else {
+            return Ok(GuestCartToken(None));
+        };
+
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| Uuid::parse_str(value.trim()).ok())
+            .ok_or_else(|| AppError::BadRequest("Invalid cart token.".to_owned()))?;
+
+        Ok(GuestCartToken(Some(token.to_string())))
+    }
+}
+
+/// Redis key holding a guest cart.
+fn guest_cart_key(token: &str) -> String {
+    format!("guest_cart:{}", token)
+}
+
+/// Loads a guest cart. An unknown or expired token is an empty cart.
+pub async fn load_guest_cart(cache: &dyn Cache, token: &str) -> Result<Vec<GuestCartItem>> {
+    let items = cache.get_json(&guest_cart_key(token)).await?;
+    Ok(items.unwrap_or_default())
+}
+
+/// Stores a guest cart, or deletes it when it is empty.
+pub async fn save_guest_cart(
+    cache: &dyn Cache,
+    token: &str,
+    items: &[GuestCartItem],
+) -> Result<()> {
+    if items.is_empty() {
+        cache.delete(&guest_cart_key(token)).await?;
+        return Ok(());
+    }
+
+    cache
+        .set_json(&guest_cart_key(token), &items, GUEST_CART_TTL)
+        .await?;
+    Ok(())
+}
+
+/// Adds a quantity of a variant to the user's cart and returns the cart line.
+/// It is a single upsert on the unique (user, variant) index, so the quantity of a variant
+/// that is already in the cart, or is added by a concurrent request, is added up rather than
+/// inserted twice. The total is capped at `MAX_CART_QUANTITY`.
+pub async fn add_cart_item<C: ConnectionTrait>(
+    db: &C,
+    user_id: i32,
+    variant_id: i32,
+    quantity: i32,
+) -> Result<cart_item::Model, DbErr> {
+    let now = Utc::now().naive_utc();
+    CartItem::insert(cart_item::ActiveModel {
+        user_id: Set(user_id),
+        variant_id: Set(variant_id),
+        quantity: Set(quantity.min(MAX_CART_QUANTITY)),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([cart_item::Column::UserId, cart_item::Column::VariantId])
+            .values([
+                (
+                    cart_item::Column::Quantity,
+                    Expr::cust_with_values(
+                        r#"LEAST("cart_items"."quantity" + "excluded"."quantity", $1)"#,
+                        [MAX_CART_QUANTITY],
+                    ),
+                ),
+                (cart_item::Column::UpdatedAt, Expr::value(now)),
+            ])
+            .to_owned(),
+    )
+    .exec_with_returning(db)
+    .await
+}
+
+/// Moves the items of a guest cart into the user's cart and deletes the guest cart.
+/// Quantities of variants already in the user's cart are added up.
+pub async fn merge_guest_cart(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    user_id: i32,
+    token: &str,
+) -> Result<()> {
+    let items = load_guest_cart(cache, token).await?;
+
+    for item in items {
+        // Variants can be deleted while they sit in a guest cart
+        if ProductVariant::find_by_id(item.variant_id)
+            .one(db)
+            .await?
+            .is_none()
+        {
+            continue;
+        }
+
+        add_cart_item(db, user_id, item.variant_id, item.quantity).await?;
+    }
+
+    cache.delete(&guest_cart_key(token)).await?;
+    Ok(())
+}
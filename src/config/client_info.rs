@@ -0,0 +1,66 @@
+use super::app_config::AppConfig;
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// A custom extractor for the caller's user agent and IP address, used to label sessions.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    Arc<AppConfig>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<AppConfig>::from_ref(state);
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let user_agent = header(USER_AGENT.as_str()).map(|value| value.chars().take(255).collect());
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let is_trusted = |ip: &IpAddr| config.trusted_proxies.contains(ip);
+
+        // The forwarding headers can be set by anyone, so they only count when they come from
+        // one of our proxies. Each proxy appends the address it saw to X-Forwarded-For, so the
+        // client is the last entry that isn't a trusted proxy.
+        let client_ip = match peer {
+            Some(peer) if is_trusted(&peer) => header("X-Forwarded-For")
+                .and_then(|value| {
+                    value
+                        .rsplit(',')
+                        .filter_map(|entry| entry.trim().parse::<IpAddr>().ok())
+                        .find(|ip| !is_trusted(ip))
+                })
+                .or_else(|| header("X-Real-IP").and_then(|value| value.parse().ok()))
+                .or(Some(peer)),
+            peer => peer,
+        };
+
+        Ok(ClientInfo {
+            user_agent,
+            ip_address: client_ip.map(|ip| ip.to_string()),
+        })
+    }
+}
@@ -1,20 +1,41 @@
-use anyhow::{Result, anyhow};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, TokenData, Validation, decode, encode};
-use once_cell::sync::OnceCell;
-use serde::{Deserialize, Serialize};
-use std::env;
+use super::jwt_keys::JwtKeyStore;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Header, TokenData, Validation, decode, decode_header, encode};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
-/// Statically get the JWT secret from environment variables
-static ENCODING_KEY: OnceCell<EncodingKey> = OnceCell::new();
-static DECODING_KEY: OnceCell<DecodingKey> = OnceCell::new();
+/// Issuer (`iss`) written into and required from every token.
+pub const JWT_ISSUER: &str = "axum_seaorm_app";
+/// Audience (`aud`) written into and required from every token.
+pub const JWT_AUDIENCE: &str = "axum_seaorm_app";
 
-/// JWT claims struct for login / access tokens
+/// The kind of token, stored in every token so one kind can't be used in place of another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    Refresh,
+    EmailVerification,
+    PasswordReset,
+    MfaPending,
+}
+
+/// JWT claims struct for login (access and refresh) tokens
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub id: String, // user_id
     pub sub: String,
     pub role: String,
+    pub sid: String, // session (refresh token family) id
+    pub ver: i32,    // user's token version when the token was issued
+    #[serde(default)]
+    pub mfa: bool, // whether the login was confirmed with a second factor
+    pub token_type: TokenType,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
     pub exp: usize,
 }
 
@@ -22,87 +43,251 @@ pub struct JwtClaims {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmailVerificationClaims {
     pub id: String,
+    pub token_type: TokenType,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+/// JWT claims struct for password reset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordResetClaims {
+    pub id: String,
+    pub ver: i32,
+    pub token_type: TokenType,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+/// JWT claims struct for a login that passed the password check and still needs a second factor
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaPendingClaims {
+    pub id: String,
+    pub ver: i32,
+    pub token_type: TokenType,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
     pub exp: usize,
 }
 
-/// Initializes JWT keys from an environment variable.
-/// Must be called once at application startup.
-pub fn init_keys() -> Result<()> {
-    let secret = env::var("JWT_SECRET").map_err(|_| anyhow!("JWT_SECRET must be set"))?;
-    ENCODING_KEY
-        .set(EncodingKey::from_secret(secret.as_bytes()))
-        .ok();
-    DECODING_KEY
-        .set(DecodingKey::from_secret(secret.as_bytes()))
-        .ok();
-    Ok(())
+/// Claims that carry a token type, so `decode_typed` can reject the wrong kind of token.
+trait TypedClaims {
+    fn token_type(&self) -> TokenType;
+}
+
+impl TypedClaims for JwtClaims {
+    fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+}
+
+impl TypedClaims for EmailVerificationClaims {
+    fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+}
+
+impl TypedClaims for PasswordResetClaims {
+    fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+}
+
+impl TypedClaims for MfaPendingClaims {
+    fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+}
+
+/// Current UNIX timestamp in seconds.
+fn current_timestamp() -> usize {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as usize
+}
+
+/// Returns the public verification keys as a JWK set.
+pub fn jwks(keys: &JwtKeyStore) -> JwkSet {
+    keys.current().jwks.clone()
+}
+
+/// Signs any claims with the active key, naming it in the `kid` header.
+fn encode_claims<T: Serialize>(
+    keys: &JwtKeyStore,
+    claims: &T,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let keys = keys.current();
+
+    let mut header = Header::new(keys.algorithm);
+    header.kid = keys.signing_kid.clone();
+    encode(&header, claims, &keys.encoding_key)
+}
+
+/// Decodes a token, checking signature, expiry, issuer, audience and token type.
+fn decode_typed<T: DeserializeOwned + TypedClaims>(
+    keys: &JwtKeyStore,
+    token: &str,
+    expected: TokenType,
+) -> Result<TokenData<T>, jsonwebtoken::errors::Error> {
+    let keys = keys.current();
+
+    // Pick the verification key named by the token; unknown key ids are rejected.
+    let header = decode_header(token)?;
+    let decoding_key = keys
+        .decoding_key(header.kid.as_deref())
+        .ok_or(jsonwebtoken::errors::ErrorKind::InvalidToken)?;
+
+    // Only the configured algorithm is accepted, whatever the header claims.
+    let mut validation = Validation::new(keys.algorithm);
+    validation.set_issuer(&[JWT_ISSUER]);
+    validation.set_audience(&[JWT_AUDIENCE]);
+
+    let token_data = decode::<T>(token, decoding_key, &validation)?;
+    if token_data.claims.token_type() != expected {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(token_data)
 }
 
-/// Creates a new login JWT with a user ID, role, and expiration time.
+/// Creates a new login JWT (access or refresh) with a user ID, role, session, token version,
+/// second factor flag and expiration time.
+#[allow(clippy::too_many_arguments)]
 pub fn create_jwt(
+    keys: &JwtKeyStore,
     user_id: &str,
     role: &str,
+    session_id: &str,
+    token_version: i32,
+    mfa: bool,
+    token_type: TokenType,
     exp_seconds: usize,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    let expiration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as usize
-        + exp_seconds;
+    let issued_at = current_timestamp();
 
     let claims = JwtClaims {
         id: user_id.to_string(),
         sub: user_id.to_string(),
         role: role.to_string(),
-        exp: expiration,
+        sid: session_id.to_string(),
+        ver: token_version,
+        mfa,
+        token_type,
+        jti: Uuid::new_v4().to_string(),
+        iss: JWT_ISSUER.to_string(),
+        aud: JWT_AUDIENCE.to_string(),
+        iat: issued_at,
+        exp: issued_at + exp_seconds,
     };
 
-    let encoding_key = ENCODING_KEY
-        .get()
-        .expect("JWT keys not initialized. Call init_keys() first.");
-
-    encode(&Header::default(), &claims, encoding_key)
+    encode_claims(keys, &claims)
 }
 
-/// Verifies a login JWT and returns the claims.
-pub fn verify_jwt(token: &str) -> Result<TokenData<JwtClaims>, jsonwebtoken::errors::Error> {
-    let decoding_key = DECODING_KEY
-        .get()
-        .expect("JWT keys not initialized. Call init_keys() first.");
-
-    decode::<JwtClaims>(token, decoding_key, &Validation::default())
+/// Verifies a login JWT of the expected type (access or refresh) and returns the claims.
+pub fn verify_jwt(
+    keys: &JwtKeyStore,
+    token: &str,
+    token_type: TokenType,
+) -> Result<TokenData<JwtClaims>, jsonwebtoken::errors::Error> {
+    decode_typed(keys, token, token_type)
 }
 
 /// Creates a JWT specifically for email verification
 pub fn create_email_verification_jwt(
+    keys: &JwtKeyStore,
     user_id: &str,
     exp_seconds: usize,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    let expiration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as usize
-        + exp_seconds;
+    let issued_at = current_timestamp();
 
     let claims = EmailVerificationClaims {
         id: user_id.to_string(),
-        exp: expiration,
+        token_type: TokenType::EmailVerification,
+        jti: Uuid::new_v4().to_string(),
+        iss: JWT_ISSUER.to_string(),
+        aud: JWT_AUDIENCE.to_string(),
+        iat: issued_at,
+        exp: issued_at + exp_seconds,
     };
 
-    let encoding_key = ENCODING_KEY
-        .get()
-        .expect("JWT keys not initialized. Call init_keys() first.");
-
-    encode(&Header::default(), &claims, encoding_key)
+    encode_claims(keys, &claims)
 }
 
 /// Verifies an email verification JWT and returns the claims.
 pub fn verify_email_verification_jwt(
+    keys: &JwtKeyStore,
     token: &str,
 ) -> Result<TokenData<EmailVerificationClaims>, jsonwebtoken::errors::Error> {
-    let decoding_key = DECODING_KEY
-        .get()
-        .expect("JWT keys not initialized. Call init_keys() first.");
+    decode_typed(keys, token, TokenType::EmailVerification)
+}
 
-    decode::<EmailVerificationClaims>(token, decoding_key, &Validation::default())
+/// Creates a JWT specifically for password reset.
+/// It is bound to the current token version, so it stops working once the password is reset.
+pub fn create_password_reset_jwt(
+    keys: &JwtKeyStore,
+    user_id: &str,
+    token_version: i32,
+    exp_seconds: usize,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let issued_at = current_timestamp();
+
+    let claims = PasswordResetClaims {
+        id: user_id.to_string(),
+        ver: token_version,
+        token_type: TokenType::PasswordReset,
+        jti: Uuid::new_v4().to_string(),
+        iss: JWT_ISSUER.to_string(),
+        aud: JWT_AUDIENCE.to_string(),
+        iat: issued_at,
+        exp: issued_at + exp_seconds,
+    };
+
+    encode_claims(keys, &claims)
+}
+
+/// Verifies a password reset JWT and returns the claims.
+pub fn verify_password_reset_jwt(
+    keys: &JwtKeyStore,
+    token: &str,
+) -> Result<TokenData<PasswordResetClaims>, jsonwebtoken::errors::Error> {
+    decode_typed(keys, token, TokenType::PasswordReset)
+}
+
+/// Creates a short-lived JWT that stands for a login waiting for its second factor.
+/// It is bound to the current token version, like the password reset token.
+pub fn create_mfa_pending_jwt(
+    keys: &JwtKeyStore,
+    user_id: &str,
+    token_version: i32,
+    exp_seconds: usize,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let issued_at = current_timestamp();
+
+    let claims = MfaPendingClaims {
+        id: user_id.to_string(),
+        ver: token_version,
+        token_type: TokenType::MfaPending,
+        jti: Uuid::new_v4().to_string(),
+        iss: JWT_ISSUER.to_string(),
+        aud: JWT_AUDIENCE.to_string(),
+        iat: issued_at,
+        exp: issued_at + exp_seconds,
+    };
+
+    encode_claims(keys, &claims)
+}
+
+/// Verifies an "mfa pending" JWT and returns the claims.
+pub fn verify_mfa_pending_jwt(
+    keys: &JwtKeyStore,
+    token: &str,
+) -> Result<TokenData<MfaPendingClaims>, jsonwebtoken::errors::Error> {
+    decode_typed(keys, token, TokenType::MfaPending)
 }
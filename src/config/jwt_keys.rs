@@ -0,0 +1,242 @@
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use rsa::RsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use super::app_config::AppConfig;
+
+/// Suffix of a public (verification) key file, named `<kid>.pub.pem`.
+const PUBLIC_KEY_SUFFIX: &str = ".pub.pem";
+/// Suffix of a private (signing) key file, named `<kid>.key.pem`.
+const PRIVATE_KEY_SUFFIX: &str = ".key.pem";
+/// DER header of an Ed25519 SubjectPublicKeyInfo: the algorithm identifier with OID
+/// 1.3.101.112, then the bit string holding the 32 byte key.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Signing and verification keys for JWTs.
+///
+/// With `HS256` one shared secret signs and verifies every token. With `RS256` or `EdDSA`
+/// the active private key signs new tokens, while every public key in the key directory
+/// stays valid for verification, so tokens signed by a previous key keep working during a
+/// rotation window.
+pub struct JwtKeys {
+    pub algorithm: Algorithm,
+    /// Key id written into the header of new tokens (`None` for HS256).
+    pub signing_kid: Option<String>,
+    pub encoding_key: EncodingKey,
+    /// Verification keys by key id.
+    decoding_keys: HashMap<String, DecodingKey>,
+    /// Verification key for tokens without a `kid` (HS256 only).
+    default_decoding_key: Option<DecodingKey>,
+    /// Public keys published at `/.well-known/jwks.json`.
+    pub jwks: JwkSet,
+}
+
+impl JwtKeys {
+    /// Finds the verification key for a token header's `kid`.
+    pub fn decoding_key(&self, kid: Option<&str>) -> Option<&DecodingKey> {
+        match kid {
+            Some(kid) => self.decoding_keys.get(kid),
+            None => self.default_decoding_key.as_ref(),
+        }
+    }
+}
+
+/// The current JWT key set, shared by every clone of the store.
+/// The set is replaced as a whole by `reload`, so keys can be rotated at runtime.
+#[derive(Clone)]
+pub struct JwtKeyStore {
+    keys: Arc<RwLock<Arc<JwtKeys>>>,
+    config: Arc<AppConfig>,
+}
+
+impl JwtKeyStore {
+    /// Loads the keys configured by the `JWT_*` settings (see `read_keys`).
+    pub fn load(config: Arc<AppConfig>) -> Result<Self> {
+        let keys = Arc::new(RwLock::new(Arc::new(read_keys(&config)?)));
+        Ok(Self { keys, config })
+    }
+
+    /// Re-reads the key files and makes them current.
+    /// Only the files are re-read: the `JWT_*` settings are the ones loaded at startup,
+    /// so the signing key id doesn't change. On error the old keys stay in use.
+    pub fn reload(&self) -> Result<()> {
+        let keys = Arc::new(read_keys(&self.config)?);
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        Ok(())
+    }
+
+    /// Returns the current key set.
+    pub fn current(&self) -> Arc<JwtKeys> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Reads the JWT keys for the configured algorithm.
+///
+/// - `JWT_ALGORITHM`: `HS256` (default), `RS256` or `EdDSA`.
+/// - `JWT_SECRET`: the shared secret for `HS256`.
+/// - `JWT_KEYS_DIR`: directory with `<kid>.pub.pem` / `<kid>.key.pem` files (default `keys`).
+/// - `JWT_ACTIVE_KID`: key id used to sign new tokens.
+fn read_keys(config: &AppConfig) -> Result<JwtKeys> {
+    let algorithm = config.jwt_algorithm.as_str();
+
+    let keys = match algorithm {
+        "HS256" => {
+            let secret = config
+                .jwt_secret
+                .as_ref()
+                .ok_or_else(|| anyhow!("JWT_SECRET must be set"))?;
+            hmac_keys(secret.expose().as_bytes())
+        }
+        "RS256" | "EdDSA" => {
+            let algorithm = if algorithm == "RS256" {
+                Algorithm::RS256
+            } else {
+                Algorithm::EdDSA
+            };
+            let active_kid = config.jwt_active_kid.as_deref().ok_or_else(|| {
+                anyhow!(
+                    "JWT_ACTIVE_KID must be set for {}",
+                    algorithm_name(algorithm)
+                )
+            })?;
+            asymmetric_keys(algorithm, Path::new(&config.jwt_keys_dir), active_kid)?
+        }
+        other => bail!("Unsupported JWT_ALGORITHM: {}", other),
+    };
+
+    Ok(keys)
+}
+
+/// Builds the key set for a shared HMAC secret.
+fn hmac_keys(secret: &[u8]) -> JwtKeys {
+    JwtKeys {
+        algorithm: Algorithm::HS256,
+        signing_kid: None,
+        encoding_key: EncodingKey::from_secret(secret),
+        decoding_keys: HashMap::new(),
+        default_decoding_key: Some(DecodingKey::from_secret(secret)),
+        // A shared secret must never be published
+        jwks: JwkSet { keys: Vec::new() },
+    }
+}
+
+/// Builds the key set from the PEM files in `keys_dir`.
+fn asymmetric_keys(algorithm: Algorithm, keys_dir: &Path, active_kid: &str) -> Result<JwtKeys> {
+    let mut decoding_keys = HashMap::new();
+    let mut jwks = Vec::new();
+
+    let entries = fs::read_dir(keys_dir)
+        .with_context(|| format!("Failed to read JWT key directory {}", keys_dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let Some(kid) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(PUBLIC_KEY_SUFFIX))
+        else {
+            continue;
+        };
+
+        let pem = fs::read(&path)
+            .with_context(|| format!("Failed to read public key {}", path.display()))?;
+        let jwk = public_jwk(algorithm, kid, &pem)
+            .with_context(|| format!("Invalid public key {}", path.display()))?;
+        decoding_keys.insert(kid.to_owned(), DecodingKey::from_jwk(&jwk)?);
+        jwks.push(jwk);
+    }
+
+    if !decoding_keys.contains_key(active_kid) {
+        bail!("No public key found for active JWT key id {}", active_kid);
+    }
+
+    let private_key_path = keys_dir.join(format!("{}{}", active_kid, PRIVATE_KEY_SUFFIX));
+    let private_pem = fs::read(&private_key_path)
+        .with_context(|| format!("Failed to read private key {}", private_key_path.display()))?;
+    let encoding_key = match algorithm {
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem)?,
+        _ => EncodingKey::from_ed_pem(&private_pem)?,
+    };
+
+    Ok(JwtKeys {
+        algorithm,
+        signing_kid: Some(active_kid.to_owned()),
+        encoding_key,
+        decoding_keys,
+        default_decoding_key: None,
+        jwks: JwkSet { keys: jwks },
+    })
+}
+
+/// Converts a PEM encoded public key into a JWK.
+fn public_jwk(algorithm: Algorithm, kid: &str, pem_bytes: &[u8]) -> Result<Jwk> {
+    let common = CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_id: Some(kid.to_owned()),
+        ..Default::default()
+    };
+
+    let pem_text = std::str::from_utf8(pem_bytes)?;
+    let jwk = match algorithm {
+        Algorithm::RS256 => {
+            let public_key = RsaPublicKey::from_public_key_pem(pem_text)?;
+            Jwk {
+                common: CommonParameters {
+                    key_algorithm: Some(KeyAlgorithm::RS256),
+                    ..common
+                },
+                algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+                }),
+            }
+        }
+        _ => {
+            // An Ed25519 SubjectPublicKeyInfo is the fixed header (with the Ed25519 OID)
+            // followed by the 32 byte raw public key
+            let der = pem::parse(pem_text)?.into_contents();
+            let Some(raw_key) = der.strip_prefix(ED25519_SPKI_PREFIX.as_slice()) else {
+                bail!("Not an Ed25519 public key");
+            };
+            if raw_key.len() != 32 {
+                bail!("Ed25519 public key must be 32 bytes");
+            }
+            Jwk {
+                common: CommonParameters {
+                    key_algorithm: Some(KeyAlgorithm::EdDSA),
+                    ..common
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(raw_key),
+                }),
+            }
+        }
+    };
+    Ok(jwk)
+}
+
+/// Name of an algorithm as used in `JWT_ALGORITHM`.
+fn algorithm_name(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::RS256 => "RS256",
+        Algorithm::EdDSA => "EdDSA",
+        _ => "HS256",
+    }
+}
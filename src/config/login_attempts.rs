@@ -0,0 +1,213 @@
+use super::cache::Cache;
+use anyhow::Result;
+
+/// Window in which failed logins are counted (seconds).
+const FAILURE_WINDOW: usize = 900;
+/// Number of failures per email after which every further attempt has to wait.
+const BACKOFF_AFTER_FAILURES: i64 = 3;
+/// Longest wait between two attempts during backoff (seconds).
+const MAX_BACKOFF: usize = 300;
+/// Number of failures per email that locks the account.
+const LOCKOUT_AFTER_FAILURES: i64 = 10;
+/// How long a locked account stays locked (seconds).
+pub const LOCKOUT_DURATION: usize = 900;
+/// Number of failures per IP address (across all accounts) that blocks the address.
+const MAX_FAILURES_PER_IP: i64 = 50;
+
+/// Whether a login attempt may go ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginThrottle {
+    Allowed,
+    /// The next attempt is allowed after the given number of seconds.
+    Backoff(i64),
+    /// The account or IP address is locked for the given number of seconds.
+    Locked(i64),
+}
+
+fn failures_key(email: &str) -> String {
+    format!("login_failures:email:{}", email.to_lowercase())
+}
+
+fn ip_failures_key(ip: &str) -> String {
+    format!("login_failures:ip:{}", ip)
+}
+
+fn backoff_key(email: &str) -> String {
+    format!("login_backoff:{}", email.to_lowercase())
+}
+
+fn lock_key(email: &str) -> String {
+    format!("login_lock:{}", email.to_lowercase())
+}
+
+/// Checks whether a login attempt for the email from the given IP address is throttled.
+pub async fn check_login_throttle(
+    cache: &dyn Cache,
+    email: &str,
+    ip: Option<&str>,
+) -> Result<LoginThrottle> {
+    let lock_ttl = cache.ttl(&lock_key(email)).await?;
+    if lock_ttl > 0 {
+        return Ok(LoginThrottle::Locked(lock_ttl));
+    }
+
+    if let Some(ip) = ip {
+        let key = ip_failures_key(ip);
+        let ip_failures: i64 = cache
+            .get(&key)
+            .await?
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+        if ip_failures >= MAX_FAILURES_PER_IP {
+            return Ok(LoginThrottle::Locked(cache.ttl(&key).await?.max(1)));
+        }
+    }
+
+    let backoff_ttl = cache.ttl(&backoff_key(email)).await?;
+    if backoff_ttl > 0 {
+        return Ok(LoginThrottle::Backoff(backoff_ttl));
+    }
+
+    Ok(LoginThrottle::Allowed)
+}
+
+/// Records a failed login for the email and IP address.
+/// Starting at `BACKOFF_AFTER_FAILURES` the wait before the next attempt doubles with every
+/// failure; at `LOCKOUT_AFTER_FAILURES` the account is locked.
+/// Returns `true` when this failure locked the account.
+pub async fn record_failed_login(cache: &dyn Cache, email: &str, ip: Option<&str>) -> Result<bool> {
+    if let Some(ip) = ip {
+        cache.incr(&ip_failures_key(ip), FAILURE_WINDOW).await?;
+    }
+
+    let failures = cache.incr(&failures_key(email), FAILURE_WINDOW).await?;
+    if failures >= LOCKOUT_AFTER_FAILURES {
+        cache
+            .set(&lock_key(email), "locked", LOCKOUT_DURATION)
+            .await?;
+        cache.delete(&failures_key(email)).await?;
+        cache.delete(&backoff_key(email)).await?;
+        return Ok(true);
+    }
+
+    if failures >= BACKOFF_AFTER_FAILURES {
+        let exponent = (failures - BACKOFF_AFTER_FAILURES).min(16) as u32;
+        let delay = 2usize.pow(exponent).min(MAX_BACKOFF);
+        cache.set(&backoff_key(email), "1", delay).await?;
+    }
+
+    Ok(false)
+}
+
+/// Forgets the failed logins of an email after a successful login.
+pub async fn clear_failed_logins(cache: &dyn Cache, email: &str) -> Result<()> {
+    cache.delete(&failures_key(email)).await?;
+    cache.delete(&backoff_key(email)).await?;
+    Ok(())
+}
+
+/// Lifts a lockout (and any backoff) of an account.
+/// The per-IP failure counter is left alone: it isn't tied to one account, and clearing it
+/// would let whoever is guessing from that address carry on against every other account.
+/// A blocked address is freed when its failure window runs out.
+pub async fn unlock_login(cache: &dyn Cache, email: &str) -> Result<()> {
+    cache.delete(&lock_key(email)).await?;
+    clear_failed_logins(cache, email).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::memory_cache::MemoryCache;
+    use std::time::Duration;
+
+    const EMAIL: &str = "user@example.com";
+    const IP: Option<&str> = Some("203.0.113.7");
+
+    /// Records `count` failed logins, returning whether the last one locked the account.
+    async fn fail(cache: &MemoryCache, count: i64) -> bool {
+        let mut locked = false;
+        for _ in 0..count {
+            locked = record_failed_login(cache, EMAIL, IP).await.unwrap();
+        }
+        locked
+    }
+
+    #[tokio::test]
+    async fn repeated_failures_lock_the_account() {
+        let cache = MemoryCache::new();
+
+        assert!(!fail(&cache, BACKOFF_AFTER_FAILURES - 1).await);
+        assert_eq!(
+            check_login_throttle(&cache, EMAIL, IP).await.unwrap(),
+            LoginThrottle::Allowed
+        );
+
+        // From then on every attempt has to wait
+        assert!(!fail(&cache, 1).await);
+        assert!(matches!(
+            check_login_throttle(&cache, EMAIL, IP).await.unwrap(),
+            LoginThrottle::Backoff(_)
+        ));
+
+        // Up to the failure that locks the account
+        assert!(!fail(&cache, LOCKOUT_AFTER_FAILURES - BACKOFF_AFTER_FAILURES - 1).await);
+        assert!(fail(&cache, 1).await);
+        match check_login_throttle(&cache, EMAIL, IP).await.unwrap() {
+            LoginThrottle::Locked(ttl) => {
+                assert!(ttl > LOCKOUT_DURATION as i64 - 5 && ttl <= LOCKOUT_DURATION as i64)
+            }
+            other => panic!("expected a lock, got {:?}", other),
+        }
+        // The email is matched case-insensitively; other accounts are unaffected
+        assert!(matches!(
+            check_login_throttle(&cache, "User@Example.com", IP)
+                .await
+                .unwrap(),
+            LoginThrottle::Locked(_)
+        ));
+        assert_eq!(
+            check_login_throttle(&cache, "other@example.com", IP)
+                .await
+                .unwrap(),
+            LoginThrottle::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn the_lock_expires() {
+        let cache = MemoryCache::new();
+        assert!(fail(&cache, LOCKOUT_AFTER_FAILURES).await);
+
+        // Let the lock run out instead of waiting `LOCKOUT_DURATION`
+        cache.set(&lock_key(EMAIL), "locked", 1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        assert_eq!(
+            check_login_throttle(&cache, EMAIL, IP).await.unwrap(),
+            LoginThrottle::Allowed
+        );
+        // The lock started a new count: the next failure is the first again
+        assert!(!fail(&cache, 1).await);
+        assert_eq!(
+            check_login_throttle(&cache, EMAIL, IP).await.unwrap(),
+            LoginThrottle::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn a_successful_login_resets_the_count() {
+        let cache = MemoryCache::new();
+        assert!(!fail(&cache, LOCKOUT_AFTER_FAILURES - 1).await);
+
+        clear_failed_logins(&cache, EMAIL).await.unwrap();
+        assert_eq!(
+            check_login_throttle(&cache, EMAIL, IP).await.unwrap(),
+            LoginThrottle::Allowed
+        );
+
+        // A full count of failures is needed to lock the account again
+        assert!(!fail(&cache, LOCKOUT_AFTER_FAILURES - 1).await);
+        assert!(fail(&cache, 1).await);
+    }
+}
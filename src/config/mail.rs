@@ -2,6 +2,9 @@ use anyhow::{Result, anyhow};
 use lettre::transport::smtp::{AsyncSmtpTransport, authentication::Credentials};
 use lettre::{AsyncTransport, Message, Tokio1Executor};
 
+use super::app_config::AppConfig;
+use crate::errors::AppError;
+
 /// Email sending client
 #[allow(dead_code)]
 pub struct EmailSender {
@@ -11,26 +14,40 @@ pub struct EmailSender {
 
 #[allow(dead_code)]
 impl EmailSender {
-    /// Initialize SMTP credentials
-    pub fn new() -> Result<Self> {
-        let smtp_host = std::env::var("MAIL_HOST")?;
-        let smtp_port: u16 = std::env::var("MAIL_PORT")?.parse()?;
-        let smtp_user = std::env::var("MAIL_USERNAME")?;
-        let smtp_pass = std::env::var("MAIL_PASSWORD")?;
-        let from_email = std::env::var("MAIL_FROM")?;
-
-        let creds = Credentials::new(smtp_user, smtp_pass);
-
-        let mailer = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp_host)
-            .port(smtp_port)
+    /// Initialize SMTP credentials from the `MAIL_*` settings
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        let (Some(smtp_host), Some(smtp_user), Some(smtp_pass), Some(from_email)) = (
+            &config.mail_host,
+            &config.mail_username,
+            &config.mail_password,
+            &config.mail_from,
+        ) else {
+            return Err(anyhow!(
+                "MAIL_HOST, MAIL_USERNAME, MAIL_PASSWORD and MAIL_FROM must be set"
+            ));
+        };
+
+        let creds = Credentials::new(smtp_user.clone(), smtp_pass.expose().to_owned());
+
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(smtp_host)
+            .port(config.mail_port)
             .credentials(creds)
             .build();
 
-        Ok(Self { mailer, from_email })
+        Ok(Self {
+            mailer,
+            from_email: from_email.clone(),
+        })
+    }
+
+    /// Asynchronously send an email. Failures are reported as `AppError::Mail`.
+    pub async fn send_email(&self, to: &str, subject: &str, body: &str) -> Result<(), AppError> {
+        self.try_send_email(to, subject, body)
+            .await
+            .map_err(AppError::Mail)
     }
 
-    /// Asynchronously send an email
-    pub async fn send_email(&self, to: &str, subject: &str, body: &str) -> Result<()> {
+    async fn try_send_email(&self, to: &str, subject: &str, body: &str) -> Result<()> {
         let email = Message::builder()
             .from(self.from_email.parse()?)
             .to(to.parse()?)
@@ -0,0 +1,376 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use axum::async_trait;
+
+use super::cache::{Cache, CacheError, CacheResult};
+use super::rate_limiter::{
+    RateLimitDecision, RateLimitStrategy, fixed_window_decision, now_millis,
+    sliding_window_decision, token_bucket_decision,
+};
+
+/// How often expired entries are swept out of the map.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A value held by the in-memory cache.
+enum Value {
+    Text(String),
+    /// Request timestamps (ms) of a sliding window, oldest first.
+    Log(VecDeque<u64>),
+    /// Token bucket state: thousandths of a token and the time of the last refill (ms).
+    Bucket {
+        tokens_milli: u64,
+        updated_at: u64,
+    },
+}
+
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+struct Store {
+    entries: HashMap<String, Entry>,
+    last_sweep: Instant,
+}
+
+impl Store {
+    /// The live entry of a key; an expired entry is dropped on the way.
+    fn live(&mut self, key: &str, now: Instant) -> Option<&mut Entry> {
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.is_expired(now))
+        {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    /// Stores a value, sweeping out expired entries every `SWEEP_INTERVAL`.
+    fn insert(&mut self, key: &str, value: Value, expires_at: Option<Instant>, now: Instant) {
+        if now.duration_since(self.last_sweep) >= SWEEP_INTERVAL {
+            self.entries.retain(|_, entry| !entry.is_expired(now));
+            self.last_sweep = now;
+        }
+        self.entries
+            .insert(key.to_owned(), Entry { value, expires_at });
+    }
+}
+
+/// An in-process cache with per-key expiry, for tests and single-node dev setups.
+///
+/// Expired keys are dropped when they are read and swept out periodically on writes, so
+/// memory is bounded by the keys that are live. Clones share the same entries. Nothing is
+/// shared between processes: with more than one app instance, use `RedisCache`.
+#[derive(Clone)]
+pub struct MemoryCache {
+    store: Arc<Mutex<Store>>,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self {
+            store: Arc::new(Mutex::new(Store {
+                entries: HashMap::new(),
+                last_sweep: Instant::now(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Store> {
+        // A panic while holding the lock can't leave an entry half written
+        self.store
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn wrong_type(key: &str) -> CacheError {
+    CacheError::WrongType(format!("The value of {} has the wrong type", key))
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        match self.lock().live(key, Instant::now()) {
+            Some(Entry {
+                value: Value::Text(value),
+                ..
+            }) => Ok(Some(value.clone())),
+            Some(_) => Err(wrong_type(key)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<()> {
+        let now = Instant::now();
+        let expires_at = now + Duration::from_secs(ttl_seconds as u64);
+        self.lock()
+            .insert(key, Value::Text(value.to_owned()), Some(expires_at), now);
+        Ok(())
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<bool> {
+        let now = Instant::now();
+        let mut store = self.lock();
+        if store.live(key, now).is_some() {
+            return Ok(false);
+        }
+        let expires_at = now + Duration::from_secs(ttl_seconds as u64);
+        store.insert(key, Value::Text(value.to_owned()), Some(expires_at), now);
+        Ok(true)
+    }
+
+    async fn delete(&self, key: &str) -> CacheResult<()> {
+        self.lock().entries.remove(key);
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        Ok(self.lock().live(key, Instant::now()).is_some())
+    }
+
+    async fn incr(&self, key: &str, ttl_seconds: usize) -> CacheResult<i64> {
+        let now = Instant::now();
+        let mut store = self.lock();
+
+        let Some(entry) = store.live(key, now) else {
+            let expires_at = now + Duration::from_secs(ttl_seconds as u64);
+            store.insert(key, Value::Text("1".to_owned()), Some(expires_at), now);
+            return Ok(1);
+        };
+        let Value::Text(value) = &mut entry.value else {
+            return Err(wrong_type(key));
+        };
+        let count = value.parse::<i64>().map_err(|_| wrong_type(key))? + 1;
+        *value = count.to_string();
+        Ok(count)
+    }
+
+    async fn ttl(&self, key: &str) -> CacheResult<i64> {
+        let now = Instant::now();
+        Ok(match self.lock().live(key, now) {
+            Some(Entry {
+                expires_at: Some(expires_at),
+                ..
+            }) => expires_at.duration_since(now).as_secs_f64().ceil() as i64,
+            Some(_) => -1,
+            None => -2,
+        })
+    }
+
+    /// Same algorithms as the Redis scripts in `rate_limiter`, run under the store lock.
+    async fn check_rate_limit(
+        &self,
+        key: &str,
+        limit: u64,
+        window_seconds: u64,
+        strategy: RateLimitStrategy,
+    ) -> CacheResult<RateLimitDecision> {
+        let now = Instant::now();
+        let now_ms = now_millis();
+        let window = Duration::from_secs(window_seconds);
+        let window_ms = window_seconds * 1000;
+        let mut store = self.lock();
+
+        match strategy {
+            RateLimitStrategy::FixedWindow => {
+                let (count, expires_at) = match store.live(key, now) {
+                    Some(Entry {
+                        value: Value::Text(value),
+                        expires_at,
+                    }) => (
+                        value.parse::<u64>().map_err(|_| wrong_type(key))? + 1,
+                        *expires_at,
+                    ),
+                    Some(_) => return Err(wrong_type(key)),
+                    None => (1, Some(now + window)),
+                };
+                store.insert(key, Value::Text(count.to_string()), expires_at, now);
+
+                let ttl_ms = expires_at.map_or(0, |expires_at| {
+                    expires_at.duration_since(now).as_millis() as i64
+                });
+                Ok(fixed_window_decision(limit, count, ttl_ms))
+            }
+            RateLimitStrategy::SlidingWindow => {
+                let mut log = match store.live(key, now) {
+                    Some(Entry {
+                        value: Value::Log(log),
+                        ..
+                    }) => std::mem::take(log),
+                    Some(_) => return Err(wrong_type(key)),
+                    None => VecDeque::new(),
+                };
+                while log
+                    .front()
+                    .is_some_and(|&at| at <= now_ms.saturating_sub(window_ms))
+                {
+                    log.pop_front();
+                }
+                let allowed = (log.len() as u64) < limit;
+                if allowed {
+                    log.push_back(now_ms);
+                }
+                let count = log.len() as u64;
+                let oldest_ms = log.front().copied().unwrap_or(now_ms);
+                store.insert(key, Value::Log(log), Some(now + window), now);
+
+                Ok(sliding_window_decision(
+                    limit, window_ms, now_ms, allowed, count, oldest_ms,
+                ))
+            }
+            RateLimitStrategy::TokenBucket => {
+                let (tokens_milli, updated_at) = match store.live(key, now) {
+                    Some(Entry {
+                        value:
+                            Value::Bucket {
+                                tokens_milli,
+                                updated_at,
+                            },
+                        ..
+                    }) => (*tokens_milli, *updated_at),
+                    Some(_) => return Err(wrong_type(key)),
+                    None => (limit * 1000, now_ms),
+                };
+                let refill = now_ms.saturating_sub(updated_at) * limit * 1000 / window_ms.max(1);
+                let mut tokens_milli = (limit * 1000).min(tokens_milli + refill);
+                let allowed = tokens_milli >= 1000;
+                if allowed {
+                    tokens_milli -= 1000;
+                }
+                store.insert(
+                    key,
+                    Value::Bucket {
+                        tokens_milli,
+                        updated_at: now_ms,
+                    },
+                    Some(now + window),
+                    now,
+                );
+
+                Ok(token_bucket_decision(
+                    limit,
+                    window_ms,
+                    allowed,
+                    tokens_milli,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Long enough for a one second TTL to run out.
+    const PAST_ONE_SECOND: Duration = Duration::from_millis(1100);
+
+    #[tokio::test]
+    async fn values_expire_after_their_ttl() {
+        let cache = MemoryCache::new();
+        cache.set("key", "value", 1).await.unwrap();
+
+        assert_eq!(cache.get("key").await.unwrap().as_deref(), Some("value"));
+        assert_eq!(cache.ttl("key").await.unwrap(), 1);
+
+        tokio::time::sleep(PAST_ONE_SECOND).await;
+
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert!(!cache.exists("key").await.unwrap());
+        assert_eq!(cache.ttl("key").await.unwrap(), -2);
+        // An expired key is free again
+        assert!(cache.set_if_absent("key", "other", 60).await.unwrap());
+        assert!(!cache.set_if_absent("key", "third", 60).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn incr_starts_a_new_window_once_the_counter_expires() {
+        let cache = MemoryCache::new();
+
+        assert_eq!(cache.incr("counter", 1).await.unwrap(), 1);
+        assert_eq!(cache.incr("counter", 1).await.unwrap(), 2);
+        // Only the first increment sets the expiry
+        assert_eq!(cache.incr("counter", 60).await.unwrap(), 3);
+        assert_eq!(cache.ttl("counter").await.unwrap(), 1);
+
+        tokio::time::sleep(PAST_ONE_SECOND).await;
+
+        assert_eq!(cache.incr("counter", 60).await.unwrap(), 1);
+        assert_eq!(cache.ttl("counter").await.unwrap(), 60);
+    }
+
+    #[tokio::test]
+    async fn fixed_window_rate_limit_resets_with_the_window() {
+        let cache = MemoryCache::new();
+        let check = || cache.check_rate_limit("limit", 2, 1, RateLimitStrategy::FixedWindow);
+
+        let first = check().await.unwrap();
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+        assert!(check().await.unwrap().allowed);
+
+        let rejected = check().await.unwrap();
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.remaining, 0);
+        assert_eq!(rejected.retry_after, 1);
+
+        tokio::time::sleep(PAST_ONE_SECOND).await;
+
+        assert!(check().await.unwrap().allowed);
+    }
+
+    #[tokio::test]
+    async fn sliding_window_and_token_bucket_stop_at_the_limit() {
+        let cache = MemoryCache::new();
+
+        for strategy in [
+            RateLimitStrategy::SlidingWindow,
+            RateLimitStrategy::TokenBucket,
+        ] {
+            let key = format!("limit:{:?}", strategy);
+            for _ in 0..3 {
+                let decision = cache.check_rate_limit(&key, 3, 60, strategy).await.unwrap();
+                assert!(decision.allowed, "{:?}", strategy);
+            }
+
+            let rejected = cache.check_rate_limit(&key, 3, 60, strategy).await.unwrap();
+            assert!(!rejected.allowed, "{:?}", strategy);
+            assert_eq!(rejected.remaining, 0, "{:?}", strategy);
+            assert!(rejected.retry_after >= 1, "{:?}", strategy);
+        }
+    }
+
+    #[tokio::test]
+    async fn values_of_another_type_are_rejected() {
+        let cache = MemoryCache::new();
+        cache
+            .check_rate_limit("limit", 3, 60, RateLimitStrategy::SlidingWindow)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            cache.get("limit").await,
+            Err(CacheError::WrongType(_))
+        ));
+        assert!(matches!(
+            cache.incr("limit", 60).await,
+            Err(CacheError::WrongType(_))
+        ));
+    }
+}
@@ -1,7 +1,24 @@
-pub mod rabbitmq;
-pub mod mail;
+pub mod app_config;
+pub mod app_state;
 pub mod auth_bearer;
-pub mod redis;
 pub mod blacklist;
+pub mod cache;
+pub mod cart;
+pub mod client_info;
 pub mod database;
 pub mod jwt;
+pub mod jwt_keys;
+pub mod login_attempts;
+pub mod mail;
+pub mod memory_cache;
+pub mod order_workflow;
+pub mod payment_provider;
+pub mod payments;
+pub mod rabbitmq;
+pub mod rate_limiter;
+pub mod redis;
+pub mod refresh_tokens;
+pub mod sessions;
+pub mod token_version;
+pub mod two_factor;
+pub mod validation;
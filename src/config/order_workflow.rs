@@ -0,0 +1,197 @@
+use crate::config::rabbitmq::{EmailJob, QueuePublisher};
+use crate::errors::AppError;
+use crate::models::{
+    order, order::Entity as Order, order_item, order_item::Entity as OrderItem,
+    order_status_history, product_variant, product_variant::Entity as ProductVariant,
+    user::Entity as User,
+};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QuerySelect, Set, TransactionTrait,
+};
+use thiserror::Error;
+use tracing::{error, info};
+
+/// Errors raised while changing the status of an order.
+#[derive(Error, Debug)]
+pub enum OrderTransitionError {
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+
+    #[error("Order not found.")]
+    NotFound,
+
+    #[error("An order cannot move from {from} to {to}.")]
+    Invalid { from: String, to: String },
+}
+
+impl From<OrderTransitionError> for AppError {
+    fn from(e: OrderTransitionError) -> Self {
+        match e {
+            OrderTransitionError::Database(e) => AppError::Database(e),
+            OrderTransitionError::NotFound => AppError::NotFound(e.to_string()),
+            OrderTransitionError::Invalid { .. } => AppError::Conflict(e.to_string()),
+        }
+    }
+}
+
+/// Returns whether a status change gives the reserved stock of the order back.
+/// That is the case for orders that will never ship.
+fn releases_stock(from: &str, to: &str) -> bool {
+    to == order::STATUS_CANCELLED || (from == order::STATUS_PAID && to == order::STATUS_REFUNDED)
+}
+
+/// Records the first history entry of a newly placed order.
+pub async fn record_order_placed<C: ConnectionTrait>(
+    db: &C,
+    order_model: &order::Model,
+) -> Result<(), DbErr> {
+    order_status_history::ActiveModel {
+        order_id: Set(order_model.id),
+        from_status: Set(None),
+        to_status: Set(order_model.status.clone()),
+        note: Set(None),
+        changed_by: Set(Some(order_model.user_id)),
+        created_at: Set(order_model.created_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// Moves an order to a new status if the workflow allows it, records the change in the
+/// order's history and notifies the customer by email.
+/// `changed_by` is the acting user, or `None` for system changes such as payments.
+pub async fn transition_order(
+    db: &DatabaseConnection,
+    queue: &QueuePublisher,
+    order_id: i32,
+    to: &str,
+    note: Option<String>,
+    changed_by: Option<i32>,
+) -> Result<order::Model, OrderTransitionError> {
+    let txn = db.begin().await?;
+
+    // Lock the order so concurrent changes are applied one after the other
+    let order_model = Order::find_by_id(order_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(OrderTransitionError::NotFound)?;
+
+    let order_model = apply_transition(&txn, order_model, to, note, changed_by).await?;
+    txn.commit().await?;
+
+    notify_status_change(db, queue, &order_model).await;
+    Ok(order_model)
+}
+
+/// Moves a locked order to a new status within the caller's transaction.
+/// The caller commits and then calls `notify_status_change`.
+pub async fn apply_transition<C: ConnectionTrait>(
+    db: &C,
+    order_model: order::Model,
+    to: &str,
+    note: Option<String>,
+    changed_by: Option<i32>,
+) -> Result<order::Model, OrderTransitionError> {
+    let order_id = order_model.id;
+    let from = order_model.status.clone();
+    if !order::can_transition(&from, to) {
+        return Err(OrderTransitionError::Invalid {
+            from,
+            to: to.to_owned(),
+        });
+    }
+
+    let now = Utc::now().naive_utc();
+    if releases_stock(&from, to) {
+        let items = OrderItem::find()
+            .filter(order_item::Column::OrderId.eq(order_id))
+            .all(db)
+            .await?;
+        for item in items {
+            // Variants deleted since checkout have no stock to return
+            let Some(variant_id) = item.variant_id else {
+                continue;
+            };
+            ProductVariant::update_many()
+                .col_expr(
+                    product_variant::Column::Stock,
+                    Expr::col(product_variant::Column::Stock).add(item.quantity),
+                )
+                .col_expr(product_variant::Column::UpdatedAt, Expr::value(now))
+                .filter(product_variant::Column::Id.eq(variant_id))
+                .exec(db)
+                .await?;
+        }
+    }
+
+    let mut order_active_model: order::ActiveModel = order_model.into();
+    order_active_model.status = Set(to.to_owned());
+    order_active_model.updated_at = Set(now);
+    let order_model = order_active_model.update(db).await?;
+
+    order_status_history::ActiveModel {
+        order_id: Set(order_id),
+        from_status: Set(Some(from.clone())),
+        to_status: Set(to.to_owned()),
+        note: Set(note),
+        changed_by: Set(changed_by),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    info!("Order {} moved from {} to {}", order_id, from, to);
+    Ok(order_model)
+}
+
+/// Queues the email telling the customer about the new status of their order.
+/// A failure is logged; it must not undo the status change.
+pub async fn notify_status_change(
+    db: &DatabaseConnection,
+    queue: &QueuePublisher,
+    order_model: &order::Model,
+) {
+    let message = match order_model.status.as_str() {
+        order::STATUS_PAID => "We received your payment. We will let you know when it ships.",
+        order::STATUS_SHIPPED => "Your order is on its way.",
+        order::STATUS_DELIVERED => "Your order was delivered. Enjoy!",
+        order::STATUS_CANCELLED => "Your order was cancelled.",
+        order::STATUS_REFUNDED => "Your order was refunded.",
+        _ => return,
+    };
+
+    let user_model = match User::find_by_id(order_model.user_id).one(db).await {
+        Ok(Some(user_model)) => user_model,
+        Ok(None) => return,
+        Err(e) => {
+            error!(
+                "Failed to load the customer of order {}: {}",
+                order_model.id, e
+            );
+            return;
+        }
+    };
+
+    let email_task = EmailJob {
+        to: user_model.email,
+        subject: format!("Order #{} is {}", order_model.id, order_model.status),
+        body: format!(
+            "<html><body><h1>Order #{} update</h1><p>Hi {},</p><p>{}</p></body></html>",
+            order_model.id, user_model.name, message
+        ),
+    };
+
+    if let Err(e) = queue.publish_email(&email_task).await {
+        error!(
+            "Failed to queue status email for order {}: {}",
+            order_model.id, e
+        );
+    }
+}
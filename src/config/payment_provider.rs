@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Result, anyhow};
+use axum::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::config::app_config::AppConfig;
+use crate::errors::AppError;
+use crate::models::payment;
+
+/// Header carrying the signature of a webhook request.
+pub const SIGNATURE_HEADER: &str = "X-Payment-Signature";
+/// How old (seconds) a signed webhook may be, so captured requests can't be replayed later.
+const SIGNATURE_TOLERANCE: i64 = 300;
+
+/// Errors raised by a payment provider.
+#[derive(Error, Debug)]
+pub enum PaymentError {
+    #[error("Payment intent not found.")]
+    NotFound,
+
+    #[error("{0}")]
+    InvalidState(String),
+
+    #[error("Invalid webhook signature.")]
+    InvalidSignature,
+
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("Payment provider error: {0}")]
+    Provider(String),
+}
+
+impl From<PaymentError> for AppError {
+    fn from(e: PaymentError) -> Self {
+        match e {
+            PaymentError::NotFound => AppError::NotFound(e.to_string()),
+            PaymentError::InvalidState(_) => AppError::Conflict(e.to_string()),
+            PaymentError::InvalidSignature | PaymentError::InvalidPayload(_) => {
+                AppError::BadRequest(e.to_string())
+            }
+            PaymentError::Provider(_) => AppError::Internal(anyhow!(e)),
+        }
+    }
+}
+
+/// A payment intent as reported by the provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentIntent {
+    pub id: String,
+    pub amount: i64, // minor units (e.g. cents)
+    pub currency: String,
+    /// One of the `payment::STATUS_*` values.
+    pub status: String,
+}
+
+/// The kind of a webhook event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventKind {
+    #[serde(rename = "payment.succeeded")]
+    Succeeded,
+    #[serde(rename = "payment.failed")]
+    Failed,
+    #[serde(rename = "payment.refunded")]
+    Refunded,
+}
+
+impl WebhookEventKind {
+    /// Name of the event type as sent by the provider.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventKind::Succeeded => "payment.succeeded",
+            WebhookEventKind::Failed => "payment.failed",
+            WebhookEventKind::Refunded => "payment.refunded",
+        }
+    }
+
+    /// The payment status the event reports.
+    pub fn payment_status(&self) -> &'static str {
+        match self {
+            WebhookEventKind::Succeeded => payment::STATUS_SUCCEEDED,
+            WebhookEventKind::Failed => payment::STATUS_FAILED,
+            WebhookEventKind::Refunded => payment::STATUS_REFUNDED,
+        }
+    }
+}
+
+/// A verified webhook event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Id of the event at the provider; the same for every delivery of an event.
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: WebhookEventKind,
+    pub intent_id: String,
+}
+
+/// A payment gateway.
+///
+/// Implementations talk to one provider. Payment state changes reach the app either as the
+/// result of `capture` / `refund` or through webhooks checked by `verify_webhook`.
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Name stored with payments so webhooks are matched to the provider that created them.
+    fn name(&self) -> &'static str;
+
+    /// Creates a payment intent for an amount. `reference` identifies the order at the provider.
+    async fn create_intent(
+        &self,
+        amount: i64,
+        currency: &str,
+        reference: &str,
+    ) -> Result<PaymentIntent, PaymentError>;
+
+    /// Captures the money of an authorized payment intent.
+    async fn capture(&self, intent_id: &str) -> Result<PaymentIntent, PaymentError>;
+
+    /// Refunds the full amount of a captured payment intent.
+    async fn refund(&self, intent_id: &str) -> Result<PaymentIntent, PaymentError>;
+
+    /// Cancels a payment intent that was not captured, so it can't be paid any more.
+    async fn cancel(&self, intent_id: &str) -> Result<PaymentIntent, PaymentError>;
+
+    /// Checks the signature of a webhook request and parses its event.
+    fn verify_webhook(&self, signature: &str, payload: &[u8])
+    -> Result<WebhookEvent, PaymentError>;
+}
+
+/// Signs a webhook payload as `t=<unix timestamp>,v1=<hex HMAC-SHA256 of "<t>.<payload>">`.
+pub fn sign_payload(secret: &[u8], timestamp: i64, payload: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Checks a signature made by `sign_payload`. The comparison is constant time and signatures
+/// older than the tolerance are rejected.
+pub fn verify_signature(
+    secret: &[u8],
+    signature: &str,
+    payload: &[u8],
+) -> Result<(), PaymentError> {
+    let mut timestamp = None;
+    let mut digest = None;
+    for part in signature.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => digest = hex::decode(value).ok(),
+            _ => {}
+        }
+    }
+    let (Some(timestamp), Some(digest)) = (timestamp, digest) else {
+        return Err(PaymentError::InvalidSignature);
+    };
+
+    if (Utc::now().timestamp() - timestamp).abs() > SIGNATURE_TOLERANCE {
+        return Err(PaymentError::InvalidSignature);
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    mac.verify_slice(&digest)
+        .map_err(|_| PaymentError::InvalidSignature)
+}
+
+/// An in-process payment provider for development and tests.
+///
+/// Intents are kept in memory and authorized as soon as they are created, so they can be
+/// captured right away. Webhooks are signed with the shared secret; use `sign_webhook` to
+/// build a request the webhook endpoint accepts.
+pub struct MockPaymentProvider {
+    secret: Vec<u8>,
+    intents: Mutex<HashMap<String, PaymentIntent>>,
+}
+
+impl MockPaymentProvider {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            intents: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the signature header value for a webhook payload.
+    pub fn sign_webhook(&self, payload: &[u8]) -> String {
+        sign_payload(&self.secret, Utc::now().timestamp(), payload)
+    }
+
+    /// Moves an intent from one status to another.
+    fn advance(
+        &self,
+        intent_id: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let mut intents = self
+            .intents
+            .lock()
+            .map_err(|_| PaymentError::Provider("Mock provider state is poisoned".to_owned()))?;
+        let intent = intents.get_mut(intent_id).ok_or(PaymentError::NotFound)?;
+        if intent.status != from {
+            return Err(PaymentError::InvalidState(format!(
+                "The payment is {}; it must be {}.",
+                intent.status, from
+            )));
+        }
+
+        intent.status = to.to_owned();
+        Ok(intent.clone())
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockPaymentProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn create_intent(
+        &self,
+        amount: i64,
+        currency: &str,
+        _reference: &str,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let intent = PaymentIntent {
+            id: format!("pi_mock_{}", Uuid::new_v4().simple()),
+            amount,
+            currency: currency.to_owned(),
+            status: payment::STATUS_REQUIRES_CAPTURE.to_owned(),
+        };
+
+        self.intents
+            .lock()
+            .map_err(|_| PaymentError::Provider("Mock provider state is poisoned".to_owned()))?
+            .insert(intent.id.clone(), intent.clone());
+        Ok(intent)
+    }
+
+    async fn capture(&self, intent_id: &str) -> Result<PaymentIntent, PaymentError> {
+        self.advance(
+            intent_id,
+            payment::STATUS_REQUIRES_CAPTURE,
+            payment::STATUS_SUCCEEDED,
+        )
+    }
+
+    async fn refund(&self, intent_id: &str) -> Result<PaymentIntent, PaymentError> {
+        self.advance(
+            intent_id,
+            payment::STATUS_SUCCEEDED,
+            payment::STATUS_REFUNDED,
+        )
+    }
+
+    async fn cancel(&self, intent_id: &str) -> Result<PaymentIntent, PaymentError> {
+        self.advance(
+            intent_id,
+            payment::STATUS_REQUIRES_CAPTURE,
+            payment::STATUS_CANCELED,
+        )
+    }
+
+    fn verify_webhook(
+        &self,
+        signature: &str,
+        payload: &[u8],
+    ) -> Result<WebhookEvent, PaymentError> {
+        verify_signature(&self.secret, signature, payload)?;
+        serde_json::from_slice(payload).map_err(|e| PaymentError::InvalidPayload(e.to_string()))
+    }
+}
+
+/// Builds the payment provider selected by `PAYMENT_PROVIDER` (only `mock` for now), with
+/// webhooks signed by `PAYMENT_WEBHOOK_SECRET`.
+pub fn provider_from_config(config: &AppConfig) -> Result<Arc<dyn PaymentProvider>> {
+    let secret = config.payment_webhook_secret.expose();
+    if secret.is_empty() {
+        return Err(anyhow!("PAYMENT_WEBHOOK_SECRET must be set"));
+    }
+
+    match config.payment_provider.as_str() {
+        "mock" => Ok(Arc::new(MockPaymentProvider::new(secret))),
+        other => Err(anyhow!("Unsupported PAYMENT_PROVIDER: {}", other)),
+    }
+}
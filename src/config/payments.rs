@@ -0,0 +1,340 @@
+use chrono::Utc;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+use tracing::{info, warn};
+
+use crate::config::app_state::AppState;
+use crate::config::order_workflow::{OrderTransitionError, apply_transition, notify_status_change};
+use crate::config::payment_provider::WebhookEvent;
+use crate::errors::AppError;
+use crate::models::{
+    order, order::Entity as Order, payment, payment::Entity as Payment, payment_event,
+    payment_event::Entity as PaymentEvent,
+};
+
+/// Returns the order status a payment status leads to, if any.
+/// A failed payment leaves the order pending so the customer can try again.
+fn order_status_for(payment_status: &str) -> Option<&'static str> {
+    match payment_status {
+        payment::STATUS_SUCCEEDED => Some(order::STATUS_PAID),
+        payment::STATUS_REFUNDED => Some(order::STATUS_REFUNDED),
+        _ => None,
+    }
+}
+
+/// Returns the open payment of a pending order, or creates a payment intent for it.
+/// Asking again for the same order hands out the same intent.
+pub async fn start_payment(state: &AppState, order_id: i32) -> Result<payment::Model, AppError> {
+    let provider = state.payments.as_ref();
+    let txn = state.db.begin().await?;
+
+    // Lock the order so concurrent requests don't create two intents
+    let order_model = Order::find_by_id(order_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found.".to_owned()))?;
+    if order_model.status != order::STATUS_PENDING {
+        return Err(AppError::Conflict(
+            "Only pending orders can be paid.".to_owned(),
+        ));
+    }
+
+    let open_payment = Payment::find()
+        .filter(payment::Column::OrderId.eq(order_id))
+        .filter(payment::Column::Provider.eq(provider.name()))
+        .filter(payment::Column::Status.eq(payment::STATUS_REQUIRES_CAPTURE))
+        .one(&txn)
+        .await?;
+    if let Some(open_payment) = open_payment {
+        return Ok(open_payment);
+    }
+
+    let intent = provider
+        .create_intent(
+            order_model.total,
+            &state.config.payment_currency,
+            &format!("order_{}", order_id),
+        )
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let payment_model = payment::ActiveModel {
+        order_id: Set(order_id),
+        provider: Set(provider.name().to_owned()),
+        intent_id: Set(intent.id),
+        amount: Set(intent.amount),
+        currency: Set(intent.currency),
+        status: Set(intent.status),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+    info!(
+        "Payment {} started for order {}",
+        payment_model.intent_id, order_id
+    );
+    Ok(payment_model)
+}
+
+/// Finds the payment of an order that is in the given status or returns a 404.
+async fn find_order_payment(
+    state: &AppState,
+    order_id: i32,
+    status: &str,
+) -> Result<payment::Model, AppError> {
+    Payment::find()
+        .filter(payment::Column::OrderId.eq(order_id))
+        .filter(payment::Column::Provider.eq(state.payments.name()))
+        .filter(payment::Column::Status.eq(status))
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("The order has no {} payment.", status)))
+}
+
+/// Captures the authorized payment of an order and marks the order as paid.
+pub async fn capture_payment(
+    state: &AppState,
+    order_id: i32,
+    changed_by: Option<i32>,
+) -> Result<payment::Model, AppError> {
+    let payment_model =
+        find_order_payment(state, order_id, payment::STATUS_REQUIRES_CAPTURE).await?;
+    let intent = state.payments.capture(&payment_model.intent_id).await?;
+    apply_payment_status(state, &intent.id, &intent.status, changed_by).await
+}
+
+/// Refunds the captured payment of an order and marks the order as refunded.
+pub async fn refund_payment(
+    state: &AppState,
+    order_id: i32,
+    changed_by: Option<i32>,
+) -> Result<payment::Model, AppError> {
+    let payment_model = find_order_payment(state, order_id, payment::STATUS_SUCCEEDED).await?;
+    let intent = state.payments.refund(&payment_model.intent_id).await?;
+    apply_payment_status(state, &intent.id, &intent.status, changed_by).await
+}
+
+/// Cancels an order along with the payment intent it has open, so the customer can't pay
+/// for it any more.
+///
+/// The intent is cancelled at the provider first, outside of any transaction: if that fails,
+/// the order stays as it was. A cancelled intent can't be captured any more, so the order
+/// can't become paid before the cancellation is committed.
+pub async fn cancel_order(
+    state: &AppState,
+    order_id: i32,
+    note: Option<String>,
+    changed_by: Option<i32>,
+) -> Result<order::Model, AppError> {
+    let provider = state.payments.as_ref();
+
+    // Don't cancel the intent of an order that can't be cancelled anyway
+    let order_model = Order::find_by_id(order_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found.".to_owned()))?;
+    if !order::can_transition(&order_model.status, order::STATUS_CANCELLED) {
+        return Err(OrderTransitionError::Invalid {
+            from: order_model.status,
+            to: order::STATUS_CANCELLED.to_owned(),
+        }
+        .into());
+    }
+
+    let open_payment = Payment::find()
+        .filter(payment::Column::OrderId.eq(order_id))
+        .filter(payment::Column::Provider.eq(provider.name()))
+        .filter(payment::Column::Status.eq(payment::STATUS_REQUIRES_CAPTURE))
+        .one(&state.db)
+        .await?;
+    let cancelled_intent = match open_payment {
+        Some(open_payment) => Some(provider.cancel(&open_payment.intent_id).await?),
+        None => None,
+    };
+
+    // Order first, then payment, like `update_payment`
+    let txn = state.db.begin().await?;
+    let order_model = Order::find_by_id(order_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found.".to_owned()))?;
+    let order_model =
+        apply_transition(&txn, order_model, order::STATUS_CANCELLED, note, changed_by).await?;
+    if let Some(intent) = cancelled_intent {
+        update_payment(
+            &txn,
+            provider.name(),
+            &intent.id,
+            &intent.status,
+            changed_by,
+        )
+        .await?;
+    }
+    txn.commit().await?;
+
+    notify_status_change(&state.db, &state.queue, &order_model).await;
+    Ok(order_model)
+}
+
+/// Applies a verified webhook event. Returns `false` when the event was applied before,
+/// so a redelivered event changes nothing.
+///
+/// An event for an unknown intent is not recorded and fails with a 404, so the provider
+/// delivers it again: the intent may belong to a payment that is still being stored.
+pub async fn apply_webhook_event(state: &AppState, event: &WebhookEvent) -> Result<bool, AppError> {
+    let provider = state.payments.as_ref();
+    let txn = state.db.begin().await?;
+
+    // The unique index on (provider, event_id) lets only the first delivery through;
+    // a concurrent delivery waits here until the first one is committed
+    let inserted = PaymentEvent::insert(payment_event::ActiveModel {
+        provider: Set(provider.name().to_owned()),
+        event_id: Set(event.id.clone()),
+        event_type: Set(event.kind.as_str().to_owned()),
+        intent_id: Set(event.intent_id.clone()),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            payment_event::Column::Provider,
+            payment_event::Column::EventId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(&txn)
+    .await?;
+    if inserted == 0 {
+        info!("Payment event {} was already applied", event.id);
+        return Ok(false);
+    }
+
+    let Some((_, changed_order)) = update_payment(
+        &txn,
+        provider.name(),
+        &event.intent_id,
+        event.kind.payment_status(),
+        None,
+    )
+    .await?
+    else {
+        // Dropping the transaction rolls back the event row
+        return Err(AppError::NotFound("Payment not found.".to_owned()));
+    };
+    txn.commit().await?;
+
+    if let Some(order_model) = changed_order {
+        notify_status_change(&state.db, &state.queue, &order_model).await;
+    }
+    Ok(true)
+}
+
+/// Moves a payment to a new status in its own transaction and notifies the customer
+/// when the order changed.
+async fn apply_payment_status(
+    state: &AppState,
+    intent_id: &str,
+    status: &str,
+    changed_by: Option<i32>,
+) -> Result<payment::Model, AppError> {
+    let txn = state.db.begin().await?;
+    let updated =
+        update_payment(&txn, state.payments.name(), intent_id, status, changed_by).await?;
+    txn.commit().await?;
+
+    let Some((payment_model, changed_order)) = updated else {
+        return Err(AppError::NotFound("Payment not found.".to_owned()));
+    };
+    if let Some(order_model) = changed_order {
+        notify_status_change(&state.db, &state.queue, &order_model).await;
+    }
+    Ok(payment_model)
+}
+
+/// Moves a payment to a new status and carries the change over to its order.
+///
+/// Changes that were already applied, or that the payment workflow does not allow (e.g. a
+/// late "succeeded" after a refund), leave everything as it is. The same goes for the order:
+/// a payment for an order that was cancelled meanwhile is recorded but must be refunded by
+/// hand. Returns `None` for an unknown intent, otherwise the payment and the order if its
+/// status changed.
+///
+/// Like every other change of an order and its payments, it locks the order before the
+/// payment, so two of them can't wait on each other's locks.
+async fn update_payment(
+    txn: &DatabaseTransaction,
+    provider_name: &str,
+    intent_id: &str,
+    status: &str,
+    changed_by: Option<i32>,
+) -> Result<Option<(payment::Model, Option<order::Model>)>, AppError> {
+    let find_payment = || {
+        Payment::find()
+            .filter(payment::Column::Provider.eq(provider_name))
+            .filter(payment::Column::IntentId.eq(intent_id))
+    };
+    let Some(order_id) = find_payment()
+        .select_only()
+        .column(payment::Column::OrderId)
+        .into_tuple::<i32>()
+        .one(txn)
+        .await?
+    else {
+        warn!(
+            "Payment intent {} of {} is unknown",
+            intent_id, provider_name
+        );
+        return Ok(None);
+    };
+
+    let order_model = Order::find_by_id(order_id)
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found.".to_owned()))?;
+    let payment_model = find_payment()
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Payment not found.".to_owned()))?;
+
+    if payment_model.status == status || !payment::can_transition(&payment_model.status, status) {
+        return Ok(Some((payment_model, None)));
+    }
+
+    let mut payment_active_model: payment::ActiveModel = payment_model.into();
+    payment_active_model.status = Set(status.to_owned());
+    payment_active_model.updated_at = Set(Utc::now().naive_utc());
+    let payment_model = payment_active_model.update(txn).await?;
+    info!("Payment {} is now {}", intent_id, status);
+
+    let Some(order_status) = order_status_for(status) else {
+        return Ok(Some((payment_model, None)));
+    };
+
+    if order_model.status == order_status {
+        return Ok(Some((payment_model, None)));
+    }
+    if !order::can_transition(&order_model.status, order_status) {
+        warn!(
+            "Payment {} is {} but order {} is {}; it needs attention",
+            intent_id, status, order_id, order_model.status
+        );
+        return Ok(Some((payment_model, None)));
+    }
+
+    let note = format!("Payment {} {}.", intent_id, status);
+    let order_model =
+        apply_transition(txn, order_model, order_status, Some(note), changed_by).await?;
+    Ok(Some((payment_model, Some(order_model))))
+}
@@ -1,7 +1,8 @@
 use crate::errors::MyError;
 use lapin::{BasicProperties, Connection, ConnectionProperties, options::*, types::FieldTable};
 use serde::Serialize;
-use std::env;
+
+use super::app_config::AppConfig;
 
 #[allow(dead_code)]
 #[derive(Serialize)]
@@ -11,48 +12,58 @@ pub struct EmailJob {
     pub body: String,
 }
 
-#[allow(dead_code)]
-pub async fn publish_to_queue(task: &EmailJob, queue: &str) -> Result<(), MyError> {
-    // Retrieve individual RabbitMQ connection parts from environment variables
-    let rabbit_user = env::var("RABBITMQ_USER").unwrap_or_else(|_| "admin".to_string());
-    let rabbit_pass = env::var("RABBITMQ_PASS").unwrap_or_else(|_| "secret123".to_string());
-    let rabbit_port = env::var("RABBITMQ_PORT").unwrap_or_else(|_| "5672".to_string());
-
-    // Construct the URL programmatically to ensure vhost is included
-    let rabbitmq_url = format!(
-        "amqp://{}:{}@rabbitmq:{}/%2f",
-        rabbit_user, rabbit_pass, rabbit_port
-    );
-
-    // Connect to RabbitMQ using the URL from the environment
-    let connection = Connection::connect(&rabbitmq_url, ConnectionProperties::default()).await?;
-    let channel = connection.create_channel().await?;
-
-    // Declare the queue (ensure it exists and is durable)
-    channel
-        .queue_declare(
-            queue,
-            QueueDeclareOptions {
-                durable: true,
-                ..Default::default()
-            },
-            FieldTable::default(),
-        )
-        .await?;
-
-    // Serialize the task into bytes
-    let payload = serde_json::to_vec(task).map_err(MyError::SerdeJsonError)?;
-
-    // Publish the task to the queue
-    channel
-        .basic_publish(
-            "",    // Default exchange
-            queue, // Queue name
-            BasicPublishOptions::default(),
-            &payload,                   // Pass the reference to the payload
-            BasicProperties::default(), // No extra properties, but you can set properties here
-        )
-        .await?;
-
-    Ok(())
+/// Publishes jobs to RabbitMQ queues.
+#[derive(Clone)]
+pub struct QueuePublisher {
+    url: String,
+    email_queue: String,
+}
+
+impl QueuePublisher {
+    /// Builds the publisher from the `RABBITMQ_*` settings.
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            url: config.rabbitmq_url(),
+            email_queue: config.email_queue.clone(),
+        }
+    }
+
+    /// Publishes an email to the queue the email worker consumes (`EMAIL_QUEUE`).
+    pub async fn publish_email(&self, task: &EmailJob) -> Result<(), MyError> {
+        self.publish(task, &self.email_queue).await
+    }
+
+    pub async fn publish(&self, task: &EmailJob, queue: &str) -> Result<(), MyError> {
+        // Connect to RabbitMQ
+        let connection = Connection::connect(&self.url, ConnectionProperties::default()).await?;
+        let channel = connection.create_channel().await?;
+
+        // Declare the queue (ensure it exists and is durable)
+        channel
+            .queue_declare(
+                queue,
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        // Serialize the task into bytes
+        let payload = serde_json::to_vec(task).map_err(MyError::SerdeJsonError)?;
+
+        // Publish the task to the queue
+        channel
+            .basic_publish(
+                "",    // Default exchange
+                queue, // Queue name
+                BasicPublishOptions::default(),
+                &payload,                   // Pass the reference to the payload
+                BasicProperties::default(), // No extra properties, but you can set properties here
+            )
+            .await?;
+
+        Ok(())
+    }
 }
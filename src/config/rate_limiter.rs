@@ -0,0 +1,293 @@
+use super::cache::CacheResult;
+use super::redis::RedisCache;
+use redis::Script;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The algorithm used to count requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitStrategy {
+    /// At most `limit` requests per fixed window; cheap, but allows bursts at window edges.
+    FixedWindow,
+    /// At most `limit` requests in any `window` long period.
+    SlidingWindow,
+    /// A bucket of `limit` tokens refilled evenly over `window`; allows short bursts.
+    TokenBucket,
+}
+
+/// The outcome of a rate limit check.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the limit is fully reset.
+    pub reset_after: u64,
+    /// Seconds until the next request is allowed (0 when allowed).
+    pub retry_after: u64,
+}
+
+/// Fixed window: one counter per window.
+/// Returns {count, ttl_ms}.
+const FIXED_WINDOW_SCRIPT: &str = r#"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('PEXPIRE', KEYS[1], ARGV[1])
+end
+return {count, redis.call('PTTL', KEYS[1])}
+"#;
+
+/// Sliding window log: a sorted set of request timestamps.
+/// Returns {allowed, count, oldest_ms}.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], 0, now - window)
+local count = redis.call('ZCARD', KEYS[1])
+local allowed = 0
+if count < limit then
+    redis.call('ZADD', KEYS[1], now, ARGV[4])
+    count = count + 1
+    allowed = 1
+end
+redis.call('PEXPIRE', KEYS[1], window)
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+local oldest_ms = now
+if oldest[2] then
+    oldest_ms = tonumber(oldest[2])
+end
+return {allowed, count, oldest_ms}
+"#;
+
+/// Token bucket: the token count and the time of the last refill in a hash.
+/// Returns {allowed, tokens_left_milli}.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local capacity = tonumber(ARGV[3])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(state[1])
+local updated_at = tonumber(state[2])
+if tokens == nil then
+    tokens = capacity * 1000
+    updated_at = now
+end
+local refill = math.floor((now - updated_at) * capacity * 1000 / window)
+tokens = math.min(capacity * 1000, tokens + refill)
+local allowed = 0
+if tokens >= 1000 then
+    tokens = tokens - 1000
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tokens, 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], window)
+return {allowed, tokens}
+"#;
+
+/// Current UNIX timestamp in milliseconds.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Rounds milliseconds up to whole seconds.
+fn to_seconds(millis: u64) -> u64 {
+    millis.div_ceil(1000)
+}
+
+/// The decision of a fixed window check that counted `count` requests in a window
+/// ending in `ttl_ms`.
+pub fn fixed_window_decision(limit: u64, count: u64, ttl_ms: i64) -> RateLimitDecision {
+    let reset_after = to_seconds(ttl_ms.max(0) as u64);
+    let allowed = count <= limit;
+    RateLimitDecision {
+        allowed,
+        limit,
+        remaining: limit.saturating_sub(count),
+        reset_after,
+        retry_after: if allowed { 0 } else { reset_after.max(1) },
+    }
+}
+
+/// The decision of a sliding window check that found `count` requests in the window,
+/// the oldest at `oldest_ms`.
+pub fn sliding_window_decision(
+    limit: u64,
+    window_ms: u64,
+    now: u64,
+    allowed: bool,
+    count: u64,
+    oldest_ms: u64,
+) -> RateLimitDecision {
+    // The oldest request in the window is the next one to drop out of it
+    let reset_after = to_seconds((oldest_ms + window_ms).saturating_sub(now));
+    RateLimitDecision {
+        allowed,
+        limit,
+        remaining: limit.saturating_sub(count),
+        reset_after,
+        retry_after: if allowed { 0 } else { reset_after.max(1) },
+    }
+}
+
+/// The decision of a token bucket check that left `tokens_milli` thousandths of a token.
+pub fn token_bucket_decision(
+    limit: u64,
+    window_ms: u64,
+    allowed: bool,
+    tokens_milli: u64,
+) -> RateLimitDecision {
+    // One token is refilled every `window / limit`
+    let refill_ms_per_token = window_ms / limit.max(1);
+    let missing_milli = (limit * 1000).saturating_sub(tokens_milli);
+    let retry_after = if allowed {
+        0
+    } else {
+        to_seconds((1000 - tokens_milli) * refill_ms_per_token / 1000).max(1)
+    };
+    RateLimitDecision {
+        allowed,
+        limit,
+        remaining: tokens_milli / 1000,
+        reset_after: to_seconds(missing_milli * refill_ms_per_token / 1000),
+        retry_after,
+    }
+}
+
+/// Runs a rate limit check on Redis. Each check is a single atomic script on the server.
+pub async fn check_with_redis(
+    cache: &RedisCache,
+    key: &str,
+    limit: u64,
+    window_seconds: u64,
+    strategy: RateLimitStrategy,
+) -> CacheResult<RateLimitDecision> {
+    let mut conn = cache.connection();
+    let window_ms = window_seconds * 1000;
+    let now = now_millis();
+
+    match strategy {
+        RateLimitStrategy::FixedWindow => {
+            let (count, ttl_ms): (u64, i64) = cache
+                .with_timeout(
+                    Script::new(FIXED_WINDOW_SCRIPT)
+                        .key(key)
+                        .arg(window_ms)
+                        .invoke_async(&mut conn),
+                )
+                .await?;
+            Ok(fixed_window_decision(limit, count, ttl_ms))
+        }
+        RateLimitStrategy::SlidingWindow => {
+            let (allowed, count, oldest_ms): (u8, u64, u64) = cache
+                .with_timeout(
+                    Script::new(SLIDING_WINDOW_SCRIPT)
+                        .key(key)
+                        .arg(now)
+                        .arg(window_ms)
+                        .arg(limit)
+                        .arg(format!("{}-{}", now, uuid::Uuid::new_v4()))
+                        .invoke_async(&mut conn),
+                )
+                .await?;
+            Ok(sliding_window_decision(
+                limit,
+                window_ms,
+                now,
+                allowed == 1,
+                count,
+                oldest_ms,
+            ))
+        }
+        RateLimitStrategy::TokenBucket => {
+            let (allowed, tokens_milli): (u8, u64) = cache
+                .with_timeout(
+                    Script::new(TOKEN_BUCKET_SCRIPT)
+                        .key(key)
+                        .arg(now)
+                        .arg(window_ms)
+                        .arg(limit)
+                        .invoke_async(&mut conn),
+                )
+                .await?;
+            Ok(token_bucket_decision(
+                limit,
+                window_ms,
+                allowed == 1,
+                tokens_milli,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_window_allows_up_to_the_limit() {
+        // (count, ttl_ms) => (allowed, remaining, reset_after, retry_after)
+        let cases = [
+            ((1, 60_000), (true, 2, 60, 0)),
+            ((3, 30_500), (true, 0, 31, 0)),
+            ((4, 30_500), (false, 0, 31, 31)),
+            // A window that is just ending still makes the caller wait a second
+            ((5, 0), (false, 0, 0, 1)),
+            // No expiry reported (-1) is treated like an ending window
+            ((4, -1), (false, 0, 0, 1)),
+        ];
+
+        for ((count, ttl_ms), (allowed, remaining, reset_after, retry_after)) in cases {
+            let decision = fixed_window_decision(3, count, ttl_ms);
+            assert_eq!(decision.allowed, allowed, "count {}", count);
+            assert_eq!(decision.limit, 3);
+            assert_eq!(decision.remaining, remaining, "count {}", count);
+            assert_eq!(decision.reset_after, reset_after, "count {}", count);
+            assert_eq!(decision.retry_after, retry_after, "count {}", count);
+        }
+    }
+
+    #[test]
+    fn sliding_window_waits_for_the_oldest_request() {
+        let now = 1_000_000;
+
+        let allowed = sliding_window_decision(3, 60_000, now, true, 2, now - 10_000);
+        assert!(allowed.allowed);
+        assert_eq!(allowed.remaining, 1);
+        assert_eq!(allowed.reset_after, 50);
+        assert_eq!(allowed.retry_after, 0);
+
+        // Full: the next request fits once the oldest one leaves the window
+        let rejected = sliding_window_decision(3, 60_000, now, false, 3, now - 59_200);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.remaining, 0);
+        assert_eq!(rejected.reset_after, 1);
+        assert_eq!(rejected.retry_after, 1);
+
+        let rejected = sliding_window_decision(3, 60_000, now, false, 3, now - 15_000);
+        assert_eq!(rejected.retry_after, 45);
+    }
+
+    #[test]
+    fn token_bucket_waits_for_the_next_token() {
+        // 6 tokens per minute: one every 10 seconds
+        let full = token_bucket_decision(6, 60_000, true, 5_000);
+        assert!(full.allowed);
+        assert_eq!(full.remaining, 5);
+        assert_eq!(full.reset_after, 10);
+        assert_eq!(full.retry_after, 0);
+
+        let empty = token_bucket_decision(6, 60_000, false, 0);
+        assert!(!empty.allowed);
+        assert_eq!(empty.remaining, 0);
+        assert_eq!(empty.reset_after, 60);
+        assert_eq!(empty.retry_after, 10);
+
+        // Part of the next token is already refilled
+        let refilling = token_bucket_decision(6, 60_000, false, 750);
+        assert_eq!(refilling.remaining, 0);
+        assert_eq!(refilling.retry_after, 3);
+    }
+}
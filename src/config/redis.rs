@@ -1,63 +1,151 @@
 use anyhow::{Result, anyhow};
-use once_cell::sync::OnceCell;
-use redis::aio::MultiplexedConnection;
-use redis::{AsyncCommands, Client, RedisResult};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use axum::async_trait;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, Client, RedisResult, Script};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::timeout;
 
-/// Shared cache type
-pub type SharedCache = Arc<Mutex<MultiplexedConnection>>;
+use super::cache::{Cache, CacheError, CacheResult};
+use super::rate_limiter::{RateLimitDecision, RateLimitStrategy, check_with_redis};
 
-/// Global cache instance
-static CACHE: OnceCell<SharedCache> = OnceCell::new();
+/// How long a single command may take before it fails.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long connecting at startup may take, retries included.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Reconnect backoff: attempt `n` waits up to `RECONNECT_FACTOR_MS * RECONNECT_EXPONENT_BASE^n`
+/// milliseconds (with jitter), for at most `RECONNECT_RETRIES` attempts.
+const RECONNECT_EXPONENT_BASE: u64 = 2;
+const RECONNECT_FACTOR_MS: u64 = 100;
+const RECONNECT_RETRIES: usize = 6;
 
-/// Initialize Redis/KeyDB client and store globally
-pub async fn init_cache(redis_url: &str) -> Result<SharedCache> {
-    let client = Client::open(redis_url).map_err(|e| anyhow!("Invalid Redis URL: {}", e))?;
+/// Increments a counter and starts its expiry with the first increment in one atomic step,
+/// so a failure between the two can't leave a counter that never expires.
+/// Returns the new count.
+const INCR_SCRIPT: &str = r#"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return count
+"#;
 
-    let conn = client
-        .get_multiplexed_tokio_connection()
+/// Redis/KeyDB cache handle.
+///
+/// Wraps a multiplexed connection that every clone shares without locking, so concurrent
+/// commands are pipelined over one socket instead of waiting for each other. A dropped
+/// connection is re-established in the background with exponential backoff; commands sent
+/// meanwhile fail fast instead of hanging, since each one is bounded by `COMMAND_TIMEOUT`.
+#[derive(Clone)]
+pub struct RedisCache {
+    conn: ConnectionManager,
+    command_timeout: Duration,
+}
+
+impl RedisCache {
+    /// Connect to Redis/KeyDB
+    pub async fn connect(redis_url: &str) -> Result<Self> {
+        let client = Client::open(redis_url).map_err(|e| anyhow!("Invalid Redis URL: {}", e))?;
+
+        let conn = timeout(
+            CONNECT_TIMEOUT,
+            ConnectionManager::new_with_backoff(
+                client,
+                RECONNECT_EXPONENT_BASE,
+                RECONNECT_FACTOR_MS,
+                RECONNECT_RETRIES,
+            ),
+        )
         .await
+        .map_err(|_| anyhow!("Timed out connecting to Redis/KeyDB"))?
         .map_err(|e| anyhow!("Failed to connect to Redis/KeyDB: {}", e))?;
 
-    let shared = Arc::new(Mutex::new(conn));
-    CACHE.set(shared.clone()).ok();
-    Ok(shared)
-}
+        Ok(Self {
+            conn,
+            command_timeout: COMMAND_TIMEOUT,
+        })
+    }
 
-/// Get global cache instance
-pub fn get_cache() -> SharedCache {
-    CACHE
-        .get()
-        .expect("Cache not initialized. Call init_cache first.")
-        .clone()
-}
+    /// A handle to the underlying connection, for commands without a helper (e.g. scripts).
+    /// Run them through `with_timeout`.
+    pub fn connection(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
 
-/// Set a value in cache with TTL (seconds)
-pub async fn set_value(key: &str, value: &str, ttl_seconds: usize) -> RedisResult<()> {
-    let cache = get_cache();
-    let mut conn = cache.lock().await;
-    conn.set_ex(key, value, ttl_seconds as u64).await
+    /// Runs a command, failing it with a timeout error when the server does not answer in time.
+    pub async fn with_timeout<T>(
+        &self,
+        command: impl Future<Output = RedisResult<T>>,
+    ) -> CacheResult<T> {
+        timeout(self.command_timeout, command)
+            .await
+            .map_err(|_| CacheError::Timeout)?
+            .map_err(CacheError::from)
+    }
 }
 
-/// Get a value from cache
-pub async fn get_value(key: &str) -> RedisResult<Option<String>> {
-    let cache = get_cache();
-    let mut conn = cache.lock().await;
-    conn.get(key).await
-}
+#[async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        let mut conn = self.connection();
+        self.with_timeout(conn.get(key)).await
+    }
 
-#[allow(dead_code)]
-/// Delete a key from cache
-pub async fn delete_key(key: &str) -> RedisResult<()> {
-    let cache = get_cache();
-    let mut conn = cache.lock().await;
-    conn.del(key).await
-}
-#[allow(dead_code)]
-/// Check if a key exists in cache
-pub async fn key_exists(key: &str) -> RedisResult<bool> {
-    let cache = get_cache();
-    let mut conn = cache.lock().await;
-    conn.exists(key).await
+    async fn set(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<()> {
+        let mut conn = self.connection();
+        self.with_timeout(conn.set_ex(key, value, ttl_seconds as u64))
+            .await
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl_seconds: usize) -> CacheResult<bool> {
+        let mut conn = self.connection();
+        let reply: Option<String> = self
+            .with_timeout(
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_seconds)
+                    .query_async(&mut conn),
+            )
+            .await?;
+        Ok(reply.is_some())
+    }
+
+    async fn delete(&self, key: &str) -> CacheResult<()> {
+        let mut conn = self.connection();
+        self.with_timeout(conn.del(key)).await
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        let mut conn = self.connection();
+        self.with_timeout(conn.exists(key)).await
+    }
+
+    async fn incr(&self, key: &str, ttl_seconds: usize) -> CacheResult<i64> {
+        let mut conn = self.connection();
+        self.with_timeout(
+            Script::new(INCR_SCRIPT)
+                .key(key)
+                .arg(ttl_seconds)
+                .invoke_async(&mut conn),
+        )
+        .await
+    }
+
+    async fn ttl(&self, key: &str) -> CacheResult<i64> {
+        let mut conn = self.connection();
+        self.with_timeout(conn.ttl(key)).await
+    }
+
+    async fn check_rate_limit(
+        &self,
+        key: &str,
+        limit: u64,
+        window_seconds: u64,
+        strategy: RateLimitStrategy,
+    ) -> CacheResult<RateLimitDecision> {
+        check_with_redis(self, key, limit, window_seconds, strategy).await
+    }
 }
@@ -0,0 +1,131 @@
+use super::cache::Cache;
+use super::sessions::revoke_session;
+use crate::models::{refresh_token, refresh_token::Entity as RefreshToken};
+use chrono::{Duration, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::warn;
+
+/// Errors raised while rotating a refresh token.
+#[derive(Error, Debug)]
+pub enum RefreshTokenError {
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+
+    #[error("Failed to revoke the session: {0}")]
+    Session(anyhow::Error),
+
+    #[error("Refresh token is not recognised.")]
+    NotFound,
+
+    #[error("Refresh token has been revoked.")]
+    Revoked,
+
+    #[error("Refresh token has already been used.")]
+    Reused,
+}
+
+/// Hashes a refresh token so the raw value is never persisted.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Records a newly issued refresh token.
+/// `parent_id` links a rotated token to the token it replaced within the same family.
+pub async fn store_refresh_token(
+    db: &DatabaseConnection,
+    user_id: i32,
+    token: &str,
+    family_id: &str,
+    parent_id: Option<i32>,
+    device_info: Option<String>,
+    ttl_seconds: i64,
+) -> Result<refresh_token::Model, DbErr> {
+    let new_token = refresh_token::ActiveModel {
+        user_id: Set(user_id),
+        family_id: Set(family_id.to_owned()),
+        token_hash: Set(hash_token(token)),
+        parent_id: Set(parent_id),
+        device_info: Set(device_info),
+        revoked: Set(false),
+        expires_at: Set((Utc::now() + Duration::seconds(ttl_seconds)).naive_utc()),
+        ..Default::default()
+    };
+
+    new_token.insert(db).await
+}
+
+/// Marks a refresh token as rotated and returns its record.
+/// Presenting a token that was already rotated revokes its whole session (the token family
+/// and the access tokens issued from it, which live for `access_token_ttl` seconds), since
+/// either the legitimate client or an attacker is replaying a stolen token.
+pub async fn rotate_refresh_token(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    token: &str,
+    access_token_ttl: usize,
+) -> Result<refresh_token::Model, RefreshTokenError> {
+    let record = RefreshToken::find()
+        .filter(refresh_token::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await?
+        .ok_or(RefreshTokenError::NotFound)?;
+
+    if record.revoked {
+        return Err(RefreshTokenError::Revoked);
+    }
+
+    // Only one request can flip `rotated_at`; a concurrent or later replay sees zero rows updated.
+    let result = RefreshToken::update_many()
+        .col_expr(
+            refresh_token::Column::RotatedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(refresh_token::Column::Id.eq(record.id))
+        .filter(refresh_token::Column::RotatedAt.is_null())
+        .exec(db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        // The family id is the session id
+        revoke_session(db, cache, &record.family_id, access_token_ttl)
+            .await
+            .map_err(RefreshTokenError::Session)?;
+        warn!(
+            target: "security",
+            user_id = record.user_id,
+            family_id = %record.family_id,
+            "Refresh token reuse detected, session revoked"
+        );
+        return Err(RefreshTokenError::Reused);
+    }
+
+    Ok(record)
+}
+
+/// Revokes every refresh token of a family.
+pub async fn revoke_family(db: &DatabaseConnection, family_id: &str) -> Result<(), DbErr> {
+    RefreshToken::update_many()
+        .col_expr(refresh_token::Column::Revoked, Expr::value(true))
+        .filter(refresh_token::Column::FamilyId.eq(family_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Revokes every refresh token issued to a user.
+pub async fn revoke_user_refresh_tokens(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<(), DbErr> {
+    RefreshToken::update_many()
+        .col_expr(refresh_token::Column::Revoked, Expr::value(true))
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use super::cache::Cache;
+use super::client_info::ClientInfo;
+use super::refresh_tokens::{revoke_family, revoke_user_refresh_tokens};
+use super::token_version::bump_token_version;
+use crate::models::{session, session::Entity as Session};
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+};
+
+/// Redis key marking a session as revoked for its remaining access tokens.
+pub fn session_revocation_key(session_id: &str) -> String {
+    format!("session_revoked:{}", session_id)
+}
+
+/// Starts a session for a new refresh token family.
+pub async fn create_session(
+    db: &DatabaseConnection,
+    session_id: &str,
+    user_id: i32,
+    client: &ClientInfo,
+) -> Result<session::Model, DbErr> {
+    let now = Utc::now().naive_utc();
+    let new_session = session::ActiveModel {
+        id: Set(session_id.to_owned()),
+        user_id: Set(user_id),
+        user_agent: Set(client.user_agent.clone()),
+        ip_address: Set(client.ip_address.clone()),
+        revoked: Set(false),
+        created_at: Set(now),
+        last_used_at: Set(now),
+    };
+
+    new_session.insert(db).await
+}
+
+/// Records that a session's refresh token was used.
+pub async fn touch_session(
+    db: &DatabaseConnection,
+    session_id: &str,
+    client: &ClientInfo,
+) -> Result<(), DbErr> {
+    let mut update = Session::update_many()
+        .col_expr(
+            session::Column::LastUsedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(session::Column::Id.eq(session_id));
+
+    if let Some(ip_address) = &client.ip_address {
+        update = update.col_expr(session::Column::IpAddress, Expr::value(ip_address.clone()));
+    }
+
+    update.exec(db).await?;
+    Ok(())
+}
+
+/// Revokes a single session: its refresh tokens stop rotating and its access tokens are rejected.
+/// `access_token_ttl` is the lifetime of the session's access tokens (seconds).
+pub async fn revoke_session(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    session_id: &str,
+    access_token_ttl: usize,
+) -> Result<()> {
+    Session::update_many()
+        .col_expr(session::Column::Revoked, Expr::value(true))
+        .filter(session::Column::Id.eq(session_id))
+        .exec(db)
+        .await?;
+
+    revoke_family(db, session_id).await?;
+
+    // Access tokens can't outlive this marker, so it expires with them.
+    cache
+        .set(
+            &session_revocation_key(session_id),
+            "revoked",
+            access_token_ttl,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Revokes every session of a user ("log out everywhere").
+/// Bumping the token version also rejects any access token that is still unexpired.
+pub async fn revoke_all_sessions(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    user_id: i32,
+) -> Result<()> {
+    Session::update_many()
+        .col_expr(session::Column::Revoked, Expr::value(true))
+        .filter(session::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    revoke_user_refresh_tokens(db, user_id).await?;
+    bump_token_version(db, cache, user_id).await?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use super::cache::Cache;
+use crate::models::{user, user::Entity as User};
+use anyhow::Result;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect};
+
+/// How long the current token version is cached (seconds).
+const TOKEN_VERSION_CACHE_TTL: usize = 3600;
+
+/// Redis key caching the current token version of a user.
+pub fn token_version_key(user_id: i32) -> String {
+    format!("token_version:{}", user_id)
+}
+
+/// Returns the user's current token version, or `None` if the user no longer exists.
+/// The value is read from Redis and only loaded from Postgres on a cache miss.
+///
+/// A loaded version is only cached if the key is still missing: a version read just before a
+/// concurrent `bump_token_version` must not overwrite the newer version the bump cached.
+pub async fn current_token_version(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    user_id: i32,
+) -> Result<Option<i32>> {
+    let cache_key = token_version_key(user_id);
+    if let Some(cached) = cache.get(&cache_key).await?
+        && let Ok(version) = cached.parse()
+    {
+        return Ok(Some(version));
+    }
+
+    let version: Option<i32> = User::find_by_id(user_id)
+        .select_only()
+        .column(user::Column::TokenVersion)
+        .into_tuple()
+        .one(db)
+        .await?;
+
+    if let Some(version) = version {
+        cache
+            .set_if_absent(&cache_key, &version.to_string(), TOKEN_VERSION_CACHE_TTL)
+            .await?;
+    }
+    Ok(version)
+}
+
+/// Increments the user's token version, which invalidates every token issued so far.
+/// Use it whenever a user's credentials or privileges change (password reset, demotion, ban).
+pub async fn bump_token_version(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    user_id: i32,
+) -> Result<()> {
+    User::update_many()
+        .col_expr(
+            user::Column::TokenVersion,
+            Expr::col(user::Column::TokenVersion).add(1),
+        )
+        .filter(user::Column::Id.eq(user_id))
+        .exec(db)
+        .await?;
+
+    // Cache the new version rather than dropping the key, so a check that loaded the old
+    // version before the update can't put it back (see `current_token_version`).
+    let version: Option<i32> = User::find_by_id(user_id)
+        .select_only()
+        .column(user::Column::TokenVersion)
+        .into_tuple()
+        .one(db)
+        .await?;
+    let cache_key = token_version_key(user_id);
+    match version {
+        Some(version) => {
+            cache
+                .set(&cache_key, &version.to_string(), TOKEN_VERSION_CACHE_TTL)
+                .await?
+        }
+        None => cache.delete(&cache_key).await?,
+    }
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use super::app_config::AppConfig;
+use super::cache::Cache;
+use super::refresh_tokens::hash_token;
+use crate::models::{recovery_code, recovery_code::Entity as RecoveryCode};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// Issuer shown in authenticator apps.
+const TOTP_ISSUER: &str = "axum_seaorm_app";
+/// Number of recovery codes handed out on enrolment.
+const RECOVERY_CODE_COUNT: usize = 10;
+/// AES-GCM nonce length (bytes).
+const NONCE_LENGTH: usize = 12;
+
+/// Whether users with the given role must log in with a second factor.
+/// Enabled for the `Admin` role by setting `ADMIN_REQUIRE_2FA=true`.
+pub fn two_factor_required_for(config: &AppConfig, role: &str) -> bool {
+    role == "Admin" && config.admin_require_2fa
+}
+
+/// Generates a new random TOTP secret (base32).
+pub fn generate_secret() -> String {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+/// Builds the TOTP generator (SHA-1, 6 digits, 30 second steps, one step of clock skew).
+fn totp(secret: &str, account_name: &str) -> Result<TOTP> {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(|e| anyhow!("Invalid TOTP secret: {:?}", e))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(TOTP_ISSUER.to_owned()),
+        account_name.to_owned(),
+    )
+    .map_err(|e| anyhow!("Invalid TOTP parameters: {:?}", e))
+}
+
+/// Returns the `otpauth://` URI an authenticator app can be set up with.
+pub fn provisioning_uri(secret: &str, email: &str) -> Result<String> {
+    Ok(totp(secret, email)?.get_url())
+}
+
+/// Checks a TOTP code. A code that was accepted once is refused for the rest of its
+/// validity window, so an intercepted code can't be replayed.
+pub async fn verify_totp_code(
+    cache: &dyn Cache,
+    user_id: i32,
+    secret: &str,
+    code: &str,
+) -> Result<bool> {
+    let code = code.trim();
+    if !totp(secret, "")?.check_current(code)? {
+        return Ok(false);
+    }
+
+    // Marking the code as used is the check, so two concurrent requests can't both pass
+    let replay_key = format!("totp_used:{}:{}", user_id, code);
+    Ok(cache.set_if_absent(&replay_key, "1", 90).await?)
+}
+
+/// Decodes the 32 byte encryption key for TOTP secrets from `TOTP_ENCRYPTION_KEY` (base64).
+pub fn encryption_key(config: &AppConfig) -> Result<Key<Aes256Gcm>> {
+    let encoded = config
+        .totp_encryption_key
+        .as_ref()
+        .ok_or_else(|| anyhow!("TOTP_ENCRYPTION_KEY must be set"))?;
+    let key = STANDARD
+        .decode(encoded.expose().trim())
+        .map_err(|e| anyhow!("TOTP_ENCRYPTION_KEY is not valid base64: {}", e))?;
+    if key.len() != 32 {
+        return Err(anyhow!("TOTP_ENCRYPTION_KEY must be 32 bytes"));
+    }
+    Ok(*Key::<Aes256Gcm>::from_slice(&key))
+}
+
+/// Encrypts a TOTP secret for storage as base64 `nonce || ciphertext`.
+pub fn encrypt_secret(config: &AppConfig, secret: &str) -> Result<String> {
+    let cipher = Aes256Gcm::new(&encryption_key(config)?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, secret.as_bytes())
+        .map_err(|_| anyhow!("Failed to encrypt TOTP secret"))?;
+
+    let mut stored = nonce.to_vec();
+    stored.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(stored))
+}
+
+/// Decrypts a TOTP secret stored by `encrypt_secret`.
+pub fn decrypt_secret(config: &AppConfig, stored: &str) -> Result<String> {
+    let stored = STANDARD
+        .decode(stored)
+        .map_err(|e| anyhow!("Stored TOTP secret is not valid base64: {}", e))?;
+    if stored.len() <= NONCE_LENGTH {
+        return Err(anyhow!("Stored TOTP secret is too short"));
+    }
+
+    let (nonce, ciphertext) = stored.split_at(NONCE_LENGTH);
+    let cipher = Aes256Gcm::new(&encryption_key(config)?);
+    let secret = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt TOTP secret"))?;
+    Ok(String::from_utf8(secret)?)
+}
+
+/// Replaces the user's recovery codes with a fresh set and returns them in plain text.
+/// Only their hashes are stored, so they can't be shown again.
+pub async fn generate_recovery_codes(db: &DatabaseConnection, user_id: i32) -> Result<Vec<String>> {
+    delete_recovery_codes(db, user_id).await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            OsRng.fill_bytes(&mut bytes);
+            let code = hex::encode(bytes);
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect();
+
+    let models = codes.iter().map(|code| recovery_code::ActiveModel {
+        user_id: Set(user_id),
+        code_hash: Set(hash_token(code)),
+        used_at: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    });
+    RecoveryCode::insert_many(models).exec(db).await?;
+
+    Ok(codes)
+}
+
+/// Deletes all recovery codes of a user.
+pub async fn delete_recovery_codes(db: &DatabaseConnection, user_id: i32) -> Result<()> {
+    RecoveryCode::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Uses up a recovery code. Returns `false` if the code is unknown or was already used.
+pub async fn redeem_recovery_code(
+    db: &DatabaseConnection,
+    user_id: i32,
+    code: &str,
+) -> Result<bool> {
+    // The update only matches an unused code, so two concurrent logins can't both use it
+    let result = RecoveryCode::update_many()
+        .col_expr(
+            recovery_code::Column::UsedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .filter(recovery_code::Column::CodeHash.eq(hash_token(&code.trim().to_lowercase())))
+        .filter(recovery_code::Column::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Checks a second factor: a TOTP code from the authenticator app or one of the recovery codes.
+pub async fn verify_second_factor(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    config: &AppConfig,
+    user_id: i32,
+    encrypted_secret: &str,
+    code: &str,
+) -> Result<bool> {
+    let code = code.trim();
+    if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+        let secret = decrypt_secret(config, encrypted_secret)?;
+        return verify_totp_code(cache, user_id, &secret, code).await;
+    }
+    redeem_recovery_code(db, user_id, code).await
+}
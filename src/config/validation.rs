@@ -0,0 +1,72 @@
+use axum::{
+    Json, async_trait,
+    extract::{FromRequest, Request, rejection::JsonRejection},
+};
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError};
+
+use crate::errors::AppError;
+use crate::utils::helper::slugify;
+
+/// A JSON body extractor that also runs the payload's `Validate` rules.
+/// Invalid payloads are rejected with a 422 listing the errors of each field.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection: JsonRejection| AppError::BadRequest(rejection.body_text()))?;
+
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+/// Minimum length of a new password.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Checks that a new password is long enough and mixes upper case letters, lower case
+/// letters and digits.
+pub fn validate_password_strength(password: &str) -> Result<(), ValidationError> {
+    let long_enough = password.chars().count() >= MIN_PASSWORD_LENGTH;
+    let has_upper = password.chars().any(|c| c.is_uppercase());
+    let has_lower = password.chars().any(|c| c.is_lowercase());
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+
+    if long_enough && has_upper && has_lower && has_digit {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("password_strength");
+    error.message = Some(
+        format!(
+            "Password must be at least {} characters long and contain an upper case letter, a lower case letter and a digit.",
+            MIN_PASSWORD_LENGTH
+        )
+        .into(),
+    );
+    Err(error)
+}
+
+/// Checks that a slug only contains lower case ASCII letters and digits separated by single
+/// hyphens.
+pub fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    if !slug.is_empty() && slug.len() <= 255 && slugify(slug) == slug {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("slug");
+    error.message = Some(
+        "Slug must only contain lower case letters and digits separated by single hyphens.".into(),
+    );
+    Err(error)
+}
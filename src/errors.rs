@@ -1,6 +1,17 @@
+use crate::config::cache::CacheError;
+use axum::{
+    Json,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
 use lapin::Error as LapinError;
+use sea_orm::{DbErr, RuntimeErr};
+use serde::Serialize;
 use serde_json::Error as SerdeJsonError;
+use std::collections::BTreeMap;
 use thiserror::Error;
+use tracing::error;
+use validator::{ValidationErrors, ValidationErrorsKind};
 
 #[derive(Error, Debug)]
 pub enum MyError {
@@ -10,3 +21,251 @@ pub enum MyError {
     #[error("Serde JSON error: {0}")]
     SerdeJsonError(#[from] SerdeJsonError),
 }
+
+/// The error type returned by every handler and middleware.
+///
+/// Client errors carry a message meant for the caller. Server errors keep their cause for
+/// the logs only; the caller just gets a generic message and the error code.
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+
+    #[error("Cache error: {0}")]
+    Cache(#[from] CacheError),
+
+    #[error("Token error: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+
+    #[error("Queue error: {0}")]
+    Queue(#[from] MyError),
+
+    /// Sending an email over SMTP failed (see `EmailSender::send_email`).
+    #[error("Mail error: {0}")]
+    Mail(anyhow::Error),
+
+    #[error("One or more fields are invalid.")]
+    Validation(#[from] ValidationErrors),
+
+    #[error("{0}")]
+    BadRequest(String),
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("This token has been revoked. Please log in again.")]
+    TokenRevoked,
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("Email address is not verified. Please verify your email to continue.")]
+    EmailNotVerified,
+
+    #[error("Two-factor authentication is required. Set it up and log in again.")]
+    TwoFactorRequired,
+
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    Locked(String),
+
+    #[error("{message}")]
+    TooManyRequests {
+        message: String,
+        retry_after: Option<u64>,
+    },
+
+    #[error("Internal error: {0}")]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<bcrypt::BcryptError> for AppError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        AppError::Internal(e.into())
+    }
+}
+
+impl AppError {
+    /// The HTTP status of the error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Database(_)
+            | AppError::Cache(_)
+            | AppError::Queue(_)
+            | AppError::Mail(_)
+            | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Jwt(_) | AppError::Unauthorized(_) | AppError::TokenRevoked => {
+                StatusCode::UNAUTHORIZED
+            }
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Forbidden(_) | AppError::EmailNotVerified | AppError::TwoFactorRequired => {
+                StatusCode::FORBIDDEN
+            }
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Locked(_) => StatusCode::LOCKED,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// A stable, machine-readable code for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database_error",
+            AppError::Cache(_) => "cache_error",
+            AppError::Jwt(_) => "invalid_token",
+            AppError::Queue(_) => "queue_error",
+            AppError::Mail(_) => "mail_error",
+            AppError::Validation(_) => "validation_failed",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::TokenRevoked => "token_revoked",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::EmailNotVerified => "email_not_verified",
+            AppError::TwoFactorRequired => "two_factor_required",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Locked(_) => "account_locked",
+            AppError::TooManyRequests { .. } => "too_many_requests",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// The message shown to the caller. Server errors never expose their cause.
+    fn detail(&self) -> String {
+        match self {
+            AppError::Jwt(_) => "The token is invalid or has expired.".to_owned(),
+            e if e.status().is_server_error() => {
+                "An internal error occurred. Please try again later.".to_owned()
+            }
+            e => e.to_string(),
+        }
+    }
+
+    /// The field-level messages of a validation error, keyed by field name.
+    /// Nested fields are keyed by their path, e.g. `variants[0].sku`.
+    fn field_errors(&self) -> Option<BTreeMap<String, Vec<String>>> {
+        let AppError::Validation(errors) = self else {
+            return None;
+        };
+
+        let mut fields = BTreeMap::new();
+        collect_field_errors("", errors, &mut fields);
+        Some(fields)
+    }
+}
+
+/// Whether a database error is a violation of a unique index (Postgres code `23505`).
+pub fn is_unique_violation(err: &DbErr) -> bool {
+    let (DbErr::Exec(RuntimeErr::SqlxError(e)) | DbErr::Query(RuntimeErr::SqlxError(e))) = err
+    else {
+        return false;
+    };
+    e.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "23505")
+}
+
+/// Maps the unique violation of a value that a concurrent request took between the
+/// availability check and the write to the same 409 as a value that was taken before.
+/// Other errors are passed on.
+///
+/// ```text
+/// model.insert(&db).await.map_err(conflict_on_unique_violation("Slug is already in use."))?;
+/// ```
+pub fn conflict_on_unique_violation(message: impl Into<String>) -> impl FnOnce(DbErr) -> AppError {
+    move |e| {
+        if is_unique_violation(&e) {
+            AppError::Conflict(message.into())
+        } else {
+            e.into()
+        }
+    }
+}
+
+/// Flattens validation errors into `fields`, prefixing nested fields with their parent's path.
+fn collect_field_errors(
+    prefix: &str,
+    errors: &ValidationErrors,
+    fields: &mut BTreeMap<String, Vec<String>>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                let messages = errors
+                    .iter()
+                    .map(|e| match &e.message {
+                        Some(message) => message.to_string(),
+                        None => format!("Failed the `{}` check.", e.code),
+                    })
+                    .collect();
+                fields.insert(path, messages);
+            }
+            ValidationErrorsKind::Struct(errors) => collect_field_errors(&path, errors, fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_field_errors(&format!("{}[{}]", path, index), errors, fields);
+                }
+            }
+        }
+    }
+}
+
+/// An RFC 7807 `application/problem+json` body.
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            error!(code = self.code(), "{}", self);
+        }
+
+        let problem = ProblemDetails {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: self.code(),
+            errors: self.field_errors(),
+        };
+
+        let mut response = (status, Json(problem)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        if let AppError::TooManyRequests {
+            retry_after: Some(seconds),
+            ..
+        } = self
+        {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
+}
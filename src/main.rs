@@ -1,54 +1,60 @@
-pub mod errors;
-use axum::Extension;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-mod app;
-mod config;
-mod models;
-mod routes;
-mod utils;
-
-use config::{database, redis};
+
+use axum_seaorm_app::config::{app_config::AppConfig, app_state::AppState, jwt_keys::JwtKeyStore};
+use axum_seaorm_app::routes;
 use dotenvy::dotenv;
 #[tokio::main]
 async fn main() {
     // Load environment variables from .env file
     dotenv().ok();
-    config::jwt::init_keys().expect("Failed to initialize JWT keys");
-    // Connect to database
-    let db = database::connect_db()
-        .await
-        .expect("Failed to connect to database");
-
-    // Initialize KeyDB/Redis cache
-    let redis_url = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
-
-    let cache = redis::init_cache(&redis_url)
-        .await
-        .expect("Failed to connect to cache");
 
     // Set up tracing for better logging and debugging
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::INFO)
         .init();
 
-    // Create main app router
-    let app = routes::create_routes()
-        .layer(Extension(db))
-        .layer(Extension(Arc::new(Mutex::new(cache))));
+    let config = AppConfig::load().expect("Failed to load the configuration");
+    let port = config.port;
 
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse()
-        .expect("Failed to parse port");
+    // Connect to the database and cache and load the JWT keys
+    let state = AppState::new(config)
+        .await
+        .expect("Failed to initialize application state");
+
+    // Reload the JWT key files on SIGHUP so verification keys can be added or retired without a
+    // restart. The active key id is read once at startup: switching it needs a restart.
+    tokio::spawn(reload_jwt_keys_on_sighup(state.jwt_keys.clone()));
+
+    // Create main app router
+    let app = routes::create_routes(state);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
 
     tracing::info!("Listening on {}", addr);
 
     axum_server::bind(addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
+
+/// Re-reads the JWT key files every time the process receives SIGHUP.
+/// The algorithm, key directory and active key id stay as configured at startup.
+async fn reload_jwt_keys_on_sighup(jwt_keys: JwtKeyStore) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            tracing::warn!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        match jwt_keys.reload() {
+            Ok(()) => tracing::info!("JWT keys reloaded"),
+            Err(e) => tracing::error!("Failed to reload JWT keys, keeping the old ones: {}", e),
+        }
+    }
+}
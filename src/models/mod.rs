@@ -1 +1,14 @@
+pub mod cart_item;
+pub mod category;
+pub mod order;
+pub mod order_item;
+pub mod order_status_history;
+pub mod payment;
+pub mod payment_event;
+pub mod product;
+pub mod product_category;
+pub mod product_variant;
+pub mod recovery_code;
+pub mod refresh_token;
+pub mod session;
 pub mod user;
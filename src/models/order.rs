@@ -0,0 +1,144 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// An order that was placed and has not been paid yet.
+pub const STATUS_PENDING: &str = "pending";
+/// An order whose payment was received.
+pub const STATUS_PAID: &str = "paid";
+/// An order that was handed to the carrier.
+pub const STATUS_SHIPPED: &str = "shipped";
+/// An order that reached the customer.
+pub const STATUS_DELIVERED: &str = "delivered";
+/// An order that was called off before it was paid. Its stock is released.
+pub const STATUS_CANCELLED: &str = "cancelled";
+/// An order whose payment was returned.
+pub const STATUS_REFUNDED: &str = "refunded";
+/// All valid order statuses.
+pub const STATUSES: [&str; 6] = [
+    STATUS_PENDING,
+    STATUS_PAID,
+    STATUS_SHIPPED,
+    STATUS_DELIVERED,
+    STATUS_CANCELLED,
+    STATUS_REFUNDED,
+];
+
+/// Returns whether an order can move from one status to another.
+pub fn can_transition(from: &str, to: &str) -> bool {
+    matches!(
+        (from, to),
+        (STATUS_PENDING, STATUS_PAID)
+            | (STATUS_PENDING, STATUS_CANCELLED)
+            | (STATUS_PAID, STATUS_SHIPPED)
+            | (STATUS_PAID, STATUS_REFUNDED)
+            | (STATUS_SHIPPED, STATUS_DELIVERED)
+            | (STATUS_DELIVERED, STATUS_REFUNDED)
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "orders")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub status: String,
+    pub total: i64, // minor units (e.g. cents)
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(has_many = "super::order_item::Entity")]
+    OrderItem,
+    #[sea_orm(has_many = "super::order_status_history::Entity")]
+    OrderStatusHistory,
+    #[sea_orm(has_many = "super::payment::Entity")]
+    Payment,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::order_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderItem.def()
+    }
+}
+
+impl Related<super::order_status_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderStatusHistory.def()
+    }
+}
+
+impl Related<super::payment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Payment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every transition an order can make.
+    const ALLOWED: [(&str, &str); 6] = [
+        (STATUS_PENDING, STATUS_PAID),
+        (STATUS_PENDING, STATUS_CANCELLED),
+        (STATUS_PAID, STATUS_SHIPPED),
+        (STATUS_PAID, STATUS_REFUNDED),
+        (STATUS_SHIPPED, STATUS_DELIVERED),
+        (STATUS_DELIVERED, STATUS_REFUNDED),
+    ];
+
+    #[test]
+    fn only_the_allowed_transitions_are_possible() {
+        for from in STATUSES {
+            for to in STATUSES {
+                assert_eq!(
+                    can_transition(from, to),
+                    ALLOWED.contains(&(from, to)),
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn forbidden_transitions_are_rejected() {
+        for (from, to) in [
+            // Money that was returned is not received again
+            (STATUS_REFUNDED, STATUS_PAID),
+            // A cancelled order is never shipped, nor paid after all
+            (STATUS_CANCELLED, STATUS_SHIPPED),
+            (STATUS_CANCELLED, STATUS_PAID),
+            // Paid orders are refunded, not cancelled
+            (STATUS_PAID, STATUS_CANCELLED),
+            // No skipping steps, no going back
+            (STATUS_PENDING, STATUS_SHIPPED),
+            (STATUS_SHIPPED, STATUS_PAID),
+            (STATUS_DELIVERED, STATUS_SHIPPED),
+            // A status is not a transition to itself, nor is an unknown status
+            (STATUS_PENDING, STATUS_PENDING),
+            (STATUS_PENDING, "lost"),
+        ] {
+            assert!(!can_transition(from, to), "{} -> {}", from, to);
+        }
+    }
+}
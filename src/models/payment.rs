@@ -0,0 +1,114 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A payment intent that was created and waits for the payment to be captured.
+pub const STATUS_REQUIRES_CAPTURE: &str = "requires_capture";
+/// A payment whose money was received.
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+/// A payment that the provider declined.
+pub const STATUS_FAILED: &str = "failed";
+/// A payment whose money was returned.
+pub const STATUS_REFUNDED: &str = "refunded";
+/// A payment intent that was called off before it was captured.
+pub const STATUS_CANCELED: &str = "canceled";
+
+/// Returns whether a payment can move from one status to another.
+pub fn can_transition(from: &str, to: &str) -> bool {
+    matches!(
+        (from, to),
+        (STATUS_REQUIRES_CAPTURE, STATUS_SUCCEEDED)
+            | (STATUS_REQUIRES_CAPTURE, STATUS_FAILED)
+            | (STATUS_REQUIRES_CAPTURE, STATUS_CANCELED)
+            | (STATUS_SUCCEEDED, STATUS_REFUNDED)
+    )
+}
+
+/// A payment of an order at a payment provider.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "payments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub order_id: i32,
+    pub provider: String,
+    pub intent_id: String, // id of the payment intent at the provider
+    pub amount: i64,       // minor units (e.g. cents)
+    pub currency: String,
+    pub status: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::order::Entity",
+        from = "Column::OrderId",
+        to = "super::order::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Order,
+}
+
+impl Related<super::order::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Order.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUSES: [&str; 5] = [
+        STATUS_REQUIRES_CAPTURE,
+        STATUS_SUCCEEDED,
+        STATUS_FAILED,
+        STATUS_REFUNDED,
+        STATUS_CANCELED,
+    ];
+
+    /// Every transition a payment can make.
+    const ALLOWED: [(&str, &str); 4] = [
+        (STATUS_REQUIRES_CAPTURE, STATUS_SUCCEEDED),
+        (STATUS_REQUIRES_CAPTURE, STATUS_FAILED),
+        (STATUS_REQUIRES_CAPTURE, STATUS_CANCELED),
+        (STATUS_SUCCEEDED, STATUS_REFUNDED),
+    ];
+
+    #[test]
+    fn only_the_allowed_transitions_are_possible() {
+        for from in STATUSES {
+            for to in STATUSES {
+                assert_eq!(
+                    can_transition(from, to),
+                    ALLOWED.contains(&(from, to)),
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn forbidden_transitions_are_rejected() {
+        for (from, to) in [
+            // A refund is final
+            (STATUS_REFUNDED, STATUS_SUCCEEDED),
+            // Only captured money can be refunded
+            (STATUS_REQUIRES_CAPTURE, STATUS_REFUNDED),
+            (STATUS_CANCELED, STATUS_REFUNDED),
+            // Failed and canceled intents can't be captured any more
+            (STATUS_FAILED, STATUS_SUCCEEDED),
+            (STATUS_CANCELED, STATUS_SUCCEEDED),
+            // Captured money is refunded, not canceled
+            (STATUS_SUCCEEDED, STATUS_CANCELED),
+            (STATUS_SUCCEEDED, STATUS_SUCCEEDED),
+        ] {
+            assert!(!can_transition(from, to), "{} -> {}", from, to);
+        }
+    }
+}
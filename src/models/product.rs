@@ -0,0 +1,58 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A product that is not shown in the catalog yet.
+pub const STATUS_DRAFT: &str = "draft";
+/// A product that is listed in the catalog.
+pub const STATUS_ACTIVE: &str = "active";
+/// A product that is no longer sold.
+pub const STATUS_ARCHIVED: &str = "archived";
+/// All valid product statuses.
+pub const STATUSES: [&str; 3] = [STATUS_DRAFT, STATUS_ACTIVE, STATUS_ARCHIVED];
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "products")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    #[sea_orm(unique)]
+    pub slug: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+    pub status: String, // one of `STATUSES`
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::product_variant::Entity")]
+    ProductVariant,
+    #[sea_orm(has_many = "super::product_category::Entity")]
+    ProductCategory,
+}
+
+impl Related<super::product_variant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductVariant.def()
+    }
+}
+
+impl Related<super::product_category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductCategory.def()
+    }
+}
+
+impl Related<super::category::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::product_category::Relation::Category.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::product_category::Relation::Product.def().rev())
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -12,6 +12,10 @@ pub struct Model {
     pub email_verified_at: Option<chrono::NaiveDateTime>,
     pub password: String, // hashed password
     pub role: String,
+    pub token_version: i32, // bumped to invalidate all issued tokens
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>, // encrypted TOTP secret
+    pub totp_enabled_at: Option<chrono::NaiveDateTime>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
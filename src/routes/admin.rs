@@ -1,47 +1,219 @@
-use axum::middleware::from_fn;
+use axum::middleware::from_fn_with_state;
 use axum::{Router, routing::delete, routing::get, routing::post, routing::put};
 
 use crate::app::controllers::admin;
-use crate::app::middleware::{admin_auth_middleware, admin_guest_middleware};
+use crate::app::middleware::{
+    admin_auth_middleware, admin_guest_middleware, email_verified_middleware,
+    rate_limit_middleware, two_factor_middleware,
+};
+use crate::config::app_state::AppState;
+use rate_limit_middleware::{RateLimit, rate_limit_middleware};
 
-pub fn admin_routes() -> Router {
+pub fn admin_routes(state: &AppState) -> Router<AppState> {
     // These routes are only accessible to unauthenticated (guest) users.
     let guest_routes = Router::new()
-        .route("/login", post(admin::auth_controller::login))
-        .route("/register", post(admin::auth_controller::register))
+        .route(
+            "/login",
+            post(admin::auth_controller::login).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("admin_login", 10, 60).sliding_window(),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/login/2fa",
+            post(admin::auth_controller::verify_two_factor_login).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("admin_login_2fa", 10, 60).sliding_window(),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/register",
+            post(admin::auth_controller::register).layer(from_fn_with_state(
+                (state.clone(), RateLimit::per_ip("admin_register", 5, 3600)),
+                rate_limit_middleware,
+            )),
+        )
         .route(
             "/verify-email/:token",
-            get(admin::auth_controller::verify_email),
+            get(admin::auth_controller::verify_email).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("admin_verify_email", 20, 60),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/verify-email/resend",
+            post(admin::auth_controller::resend_verification_email).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("admin_verify_email_resend", 5, 3600),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/forgot-password",
+            post(admin::auth_controller::forgot_password).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("admin_forgot_password", 5, 3600),
+                ),
+                rate_limit_middleware,
+            )),
         )
-        .layer(from_fn(admin_guest_middleware::admin_guest_middleware));
+        .route(
+            "/reset-password",
+            post(admin::auth_controller::reset_password).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("admin_reset_password", 10, 3600),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .layer(from_fn_with_state(
+            state.clone(),
+            admin_guest_middleware::admin_guest_middleware,
+        ));
+
+    // The refresh token itself is the credential here, so the access token may have expired.
+    let refresh_routes = Router::new().route(
+        "/refresh-token",
+        post(admin::auth_controller::refresh_token).layer(from_fn_with_state(
+            (
+                state.clone(),
+                RateLimit::per_ip("admin_refresh_token", 30, 60),
+            ),
+            rate_limit_middleware,
+        )),
+    );
 
-    // These routes are accessible to any logged-in admin (token is valid and not blacklisted).
+    // Routes that check a TOTP or recovery code share one cap per admin, like the 5 attempts
+    // a login gets per MFA token, so a stolen access token can't be used to guess codes.
+    let two_factor_code_routes = Router::new()
+        .route("/2fa/confirm", post(admin::two_factor_controller::confirm))
+        .route("/2fa/disable", post(admin::two_factor_controller::disable))
+        .route(
+            "/2fa/recovery-codes",
+            post(admin::two_factor_controller::regenerate_recovery_codes),
+        )
+        .layer(from_fn_with_state(
+            (state.clone(), RateLimit::per_user("admin_2fa_code", 5, 900)),
+            rate_limit_middleware,
+        ));
+
+    // These routes are accessible to any logged-in admin, even one who still has to set up
+    // a required second factor.
+    let account_routes = Router::new()
+        .route("/logout", post(admin::auth_controller::logout))
+        .route("/2fa/setup", post(admin::two_factor_controller::setup))
+        .merge(two_factor_code_routes)
+        .layer(from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware::admin_auth_middleware,
+        ));
+
+    // These routes are accessible to any logged-in admin (token is valid and not blacklisted)
+    // who passed two-factor authentication where it is required.
     let auth_routes = Router::new()
         .route(
             "/dashboard",
             get(admin::dashboard_controller::admin_dashboard),
         )
-        .route(
-            "/refresh-token",
-            post(admin::auth_controller::refresh_token),
-        )
-        .route("/logout", post(admin::auth_controller::logout))
-        .layer(from_fn(admin_auth_middleware::admin_auth_middleware));
+        .route("/logout-all", post(admin::session_controller::logout_all))
+        .route("/sessions", get(admin::session_controller::index))
+        .route("/sessions/:id", delete(admin::session_controller::destroy))
+        .route("/users/:id/unlock", post(admin::user_controller::unlock))
+        .layer(from_fn_with_state(
+            state.clone(),
+            two_factor_middleware::two_factor_middleware,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware::admin_auth_middleware,
+        ));
 
-    // The middleware is layered: first it checks for valid auth, then for email verification.
+    // The middleware is layered: first it checks for valid auth, then two-factor authentication,
+    // then email verification.
     let verified_routes = Router::new()
         .nest(
             "/categories",
             Router::new()
-                .route("/", get(admin::category_controller::index))
+                .route(
+                    "/",
+                    get(admin::category_controller::index).layer(from_fn_with_state(
+                        (
+                            state.clone(),
+                            RateLimit::per_user("admin_category_index", 60, 60).token_bucket(),
+                        ),
+                        rate_limit_middleware,
+                    )),
+                )
                 .route("/", post(admin::category_controller::store))
+                .route("/:id", get(admin::category_controller::show))
                 .route("/:id", put(admin::category_controller::update))
                 .route("/:id", delete(admin::category_controller::destroy)),
         )
-        .layer(from_fn(admin_auth_middleware::admin_auth_middleware));
+        .nest(
+            "/products",
+            Router::new()
+                .route(
+                    "/",
+                    get(admin::product_controller::index).post(admin::product_controller::store),
+                )
+                .route(
+                    "/:id",
+                    get(admin::product_controller::show)
+                        .put(admin::product_controller::update)
+                        .delete(admin::product_controller::destroy),
+                )
+                .route(
+                    "/:id/variants",
+                    post(admin::product_controller::store_variant),
+                )
+                .route(
+                    "/:id/variants/:variant_id",
+                    put(admin::product_controller::update_variant)
+                        .delete(admin::product_controller::destroy_variant),
+                ),
+        )
+        .nest(
+            "/orders",
+            Router::new()
+                .route("/", get(admin::order_controller::index))
+                .route("/:id", get(admin::order_controller::show))
+                .route("/:id/status", post(admin::order_controller::update_status))
+                .route(
+                    "/:id/capture",
+                    post(admin::order_controller::capture_payment),
+                )
+                .route("/:id/refund", post(admin::order_controller::refund_payment)),
+        )
+        .layer(from_fn_with_state(
+            state.clone(),
+            email_verified_middleware::email_verified_middleware,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            two_factor_middleware::two_factor_middleware,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware::admin_auth_middleware,
+        ));
 
     Router::new()
         .merge(guest_routes)
+        .merge(refresh_routes)
+        .merge(account_routes)
         .merge(auth_routes)
         .merge(verified_routes)
 }
@@ -1,7 +1,162 @@
-use axum::{Router, routing::get};
+use axum::middleware::from_fn_with_state;
+use axum::{Router, routing::delete, routing::get, routing::patch, routing::post, routing::put};
 
 // এখানে আমরা একটি একক মডিউল থেকে সব হ্যান্ডলার ইম্পোর্ট করছি।
 use crate::app::controllers::customer;
-pub fn customer_routes() -> Router {
-    Router::new().route("/profile", get(customer::auth_controller::profile))
+use crate::app::middleware::{
+    customer_auth_middleware, customer_guest_middleware, email_verified_middleware,
+    rate_limit_middleware,
+};
+use crate::config::app_state::AppState;
+use rate_limit_middleware::{RateLimit, rate_limit_middleware};
+
+pub fn customer_routes(state: &AppState) -> Router<AppState> {
+    // These routes are only accessible to unauthenticated (guest) customers.
+    let guest_routes = Router::new()
+        .route(
+            "/login",
+            post(customer::auth_controller::login).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("customer_login", 10, 60).sliding_window(),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/register",
+            post(customer::auth_controller::register).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("customer_register", 5, 3600),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/verify-email/:token",
+            get(customer::auth_controller::verify_email).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("customer_verify_email", 20, 60),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/verify-email/resend",
+            post(customer::auth_controller::resend_verification_email).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("customer_verify_email_resend", 5, 3600),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/forgot-password",
+            post(customer::auth_controller::forgot_password).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("customer_forgot_password", 5, 3600),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route(
+            "/reset-password",
+            post(customer::auth_controller::reset_password).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_ip("customer_reset_password", 10, 3600),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .route("/guest-cart", get(customer::cart_controller::guest_show))
+        .route(
+            "/guest-cart/items",
+            post(customer::cart_controller::guest_add_item),
+        )
+        .route(
+            "/guest-cart/items/:variant_id",
+            put(customer::cart_controller::guest_update_item)
+                .delete(customer::cart_controller::guest_remove_item),
+        )
+        .layer(from_fn_with_state(
+            state.clone(),
+            customer_guest_middleware::customer_guest_middleware,
+        ));
+
+    // The refresh token itself is the credential here, so the access token may have expired.
+    let refresh_routes = Router::new().route(
+        "/refresh-token",
+        post(customer::auth_controller::refresh_token).layer(from_fn_with_state(
+            (
+                state.clone(),
+                RateLimit::per_ip("customer_refresh_token", 30, 60),
+            ),
+            rate_limit_middleware,
+        )),
+    );
+
+    // These routes are accessible to any logged-in customer.
+    let auth_routes = Router::new()
+        .route(
+            "/profile",
+            get(customer::auth_controller::profile).put(customer::auth_controller::update_profile),
+        )
+        .route(
+            "/profile/password",
+            patch(customer::auth_controller::change_password),
+        )
+        .route("/logout", post(customer::auth_controller::logout))
+        .route(
+            "/logout-all",
+            post(customer::session_controller::logout_all),
+        )
+        .route("/sessions", get(customer::session_controller::index))
+        .route(
+            "/sessions/:id",
+            delete(customer::session_controller::destroy),
+        )
+        .route("/cart", get(customer::cart_controller::show))
+        .route("/cart/items", post(customer::cart_controller::add_item))
+        .route(
+            "/cart/items/:variant_id",
+            put(customer::cart_controller::update_item)
+                .delete(customer::cart_controller::remove_item),
+        )
+        .layer(from_fn_with_state(
+            state.clone(),
+            customer_auth_middleware::customer_auth_middleware,
+        ));
+
+    // Placing an order also needs a verified email address.
+    let verified_routes = Router::new()
+        .route("/checkout", post(customer::checkout_controller::checkout))
+        .route(
+            "/orders/:id/payment",
+            post(customer::payment_controller::pay),
+        )
+        .layer(from_fn_with_state(
+            state.clone(),
+            email_verified_middleware::email_verified_middleware,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            customer_auth_middleware::customer_auth_middleware,
+        ));
+
+    // The catalog is public.
+    let catalog_routes = Router::new()
+        .route("/products", get(customer::product_controller::index))
+        .route("/products/:slug", get(customer::product_controller::show));
+
+    Router::new()
+        .merge(guest_routes)
+        .merge(refresh_routes)
+        .merge(auth_routes)
+        .merge(verified_routes)
+        .merge(catalog_routes)
 }
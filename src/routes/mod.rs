@@ -1,11 +1,30 @@
-use axum::{Router, routing::get};
+use axum::middleware::from_fn_with_state;
+use axum::{Router, routing::get, routing::post};
+
+use crate::app::controllers::{payment_webhook_controller, well_known_controller};
+use crate::app::middleware::rate_limit_middleware::{RateLimit, rate_limit_middleware};
+use crate::config::app_state::AppState;
 
 pub mod admin;
 pub mod customer;
 
-pub fn create_routes() -> Router {
+/// Builds the application router with its state.
+pub fn create_routes(state: AppState) -> Router {
     Router::new()
         .route("/", get(|| async { "Hello, world!" }))
-        .nest("/admin", admin::admin_routes())
-        .nest("/customer", customer::customer_routes())
+        .route("/.well-known/jwks.json", get(well_known_controller::jwks))
+        .route(
+            "/payments/webhook",
+            // The provider calls from many addresses, so the cap is for the route as a whole
+            post(payment_webhook_controller::handle).layer(from_fn_with_state(
+                (
+                    state.clone(),
+                    RateLimit::per_route("payment_webhook", 600, 60),
+                ),
+                rate_limit_middleware,
+            )),
+        )
+        .nest("/admin", admin::admin_routes(&state))
+        .nest("/customer", customer::customer_routes(&state))
+        .with_state(state)
 }
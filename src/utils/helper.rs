@@ -1,14 +1,19 @@
-use std::env;
-
-#[allow(dead_code)]
-pub fn get_rabbitmq_url() -> String {
-    let rabbit_user = env::var("RABBITMQ_USER").unwrap_or_else(|_| "admin".to_string());
-    let rabbit_pass = env::var("RABBITMQ_PASS").unwrap_or_else(|_| "secret123".to_string());
-    let rabbit_port = env::var("RABBITMQ_PORT").unwrap_or_else(|_| "5672".to_string());
+/// Turns a name into a URL slug: lower case ASCII letters and digits separated by single hyphens.
+pub fn slugify(value: &str) -> String {
+    let mut slug = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_owned()
+}
 
-    // vhost অন্তর্ভুক্ত করে প্রোগ্রাম্যাটিকভাবে URL তৈরি করা হয়
-    format!(
-        "amqp://{}:{}@rabbitmq:{}/%2f",
-        rabbit_user, rabbit_pass, rabbit_port
-    )
+/// Formats an amount in minor units (e.g. cents) with two decimals, e.g. `1999` as `19.99`.
+pub fn format_price(minor_units: i64) -> String {
+    let sign = if minor_units < 0 { "-" } else { "" };
+    let amount = minor_units.unsigned_abs();
+    format!("{}{}.{:02}", sign, amount / 100, amount % 100)
 }
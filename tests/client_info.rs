@@ -0,0 +1,62 @@
+//! The client IP address only comes from the forwarding headers when a trusted proxy sent them.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::Request;
+
+use axum_seaorm_app::config::app_config::AppConfig;
+use axum_seaorm_app::config::client_info::ClientInfo;
+
+const PROXY: &str = "10.0.0.2:443";
+
+async fn client_ip(peer: &str, headers: &[(&str, &str)]) -> Option<String> {
+    let config = Arc::new(AppConfig {
+        trusted_proxies: vec!["10.0.0.2".parse().unwrap()],
+        ..AppConfig::default()
+    });
+
+    let mut request = Request::builder().uri("/");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let (mut parts, ()) = request.body(()).unwrap().into_parts();
+    parts
+        .extensions
+        .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+
+    let client = match ClientInfo::from_request_parts(&mut parts, &config).await {
+        Ok(client) => client,
+        Err(infallible) => match infallible {},
+    };
+    client.ip_address
+}
+
+#[tokio::test]
+async fn forwarding_headers_from_other_peers_are_ignored() {
+    let ip = client_ip(
+        "203.0.113.7:5000",
+        &[
+            ("X-Forwarded-For", "198.51.100.1"),
+            ("X-Real-IP", "198.51.100.2"),
+        ],
+    )
+    .await;
+
+    assert_eq!(ip.as_deref(), Some("203.0.113.7"));
+}
+
+#[tokio::test]
+async fn trusted_proxy_passes_on_the_client_address() {
+    // A client can put anything in front of the entry its proxy appends
+    let ip = client_ip(PROXY, &[("X-Forwarded-For", "198.51.100.1, 203.0.113.7")]).await;
+    assert_eq!(ip.as_deref(), Some("203.0.113.7"));
+
+    let ip = client_ip(PROXY, &[("X-Real-IP", "203.0.113.7")]).await;
+    assert_eq!(ip.as_deref(), Some("203.0.113.7"));
+
+    // Without a forwarding header the proxy itself is the client
+    let ip = client_ip(PROXY, &[]).await;
+    assert_eq!(ip.as_deref(), Some("10.0.0.2"));
+}
@@ -0,0 +1,300 @@
+//! Payment webhooks: only correctly signed, recent requests are accepted, and an event is
+//! applied once however often the provider delivers it.
+//!
+//! The delivery tests need a migrated Postgres database. Point `TEST_DATABASE_URL` at one
+//! and run `cargo test --test payment_webhooks -- --ignored`.
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Database, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, Set,
+};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use axum_seaorm_app::config::app_config::{AppConfig, CacheBackend, Secret};
+use axum_seaorm_app::config::app_state::AppState;
+use axum_seaorm_app::config::jwt_keys::JwtKeyStore;
+use axum_seaorm_app::config::memory_cache::MemoryCache;
+use axum_seaorm_app::config::payment_provider::{
+    MockPaymentProvider, PaymentError, PaymentProvider, SIGNATURE_HEADER, sign_payload,
+    verify_signature,
+};
+use axum_seaorm_app::config::rabbitmq::QueuePublisher;
+use axum_seaorm_app::models::{
+    order, order::Entity as Order, order_status_history,
+    order_status_history::Entity as OrderStatusHistory, payment, payment_event,
+    payment_event::Entity as PaymentEvent, user,
+};
+use axum_seaorm_app::routes::create_routes;
+
+const WEBHOOK_SECRET: &str = "payment_webhook_secret";
+
+fn event_payload(event_id: &str, intent_id: &str) -> Vec<u8> {
+    json!({ "id": event_id, "type": "payment.succeeded", "intent_id": intent_id })
+        .to_string()
+        .into_bytes()
+}
+
+#[test]
+fn valid_signature_is_accepted() {
+    let payload = event_payload("evt_1", "pi_1");
+    let signature = sign_payload(WEBHOOK_SECRET.as_bytes(), Utc::now().timestamp(), &payload);
+
+    verify_signature(WEBHOOK_SECRET.as_bytes(), &signature, &payload).unwrap();
+
+    // The provider signs and parses the same way
+    let provider = MockPaymentProvider::new(WEBHOOK_SECRET);
+    let event = provider
+        .verify_webhook(&provider.sign_webhook(&payload), &payload)
+        .unwrap();
+    assert_eq!(event.id, "evt_1");
+    assert_eq!(event.intent_id, "pi_1");
+}
+
+#[test]
+fn tampered_signature_is_rejected() {
+    let payload = event_payload("evt_1", "pi_1");
+    let signature = sign_payload(WEBHOOK_SECRET.as_bytes(), Utc::now().timestamp(), &payload);
+
+    // Another payload under the same signature
+    let tampered_payload = event_payload("evt_1", "pi_2");
+    assert!(matches!(
+        verify_signature(WEBHOOK_SECRET.as_bytes(), &signature, &tampered_payload),
+        Err(PaymentError::InvalidSignature)
+    ));
+
+    // A changed digest
+    let last = signature.chars().last().unwrap();
+    let flipped = if last == '0' { '1' } else { '0' };
+    let tampered_signature = format!("{}{}", &signature[..signature.len() - 1], flipped);
+    assert!(matches!(
+        verify_signature(WEBHOOK_SECRET.as_bytes(), &tampered_signature, &payload),
+        Err(PaymentError::InvalidSignature)
+    ));
+
+    // Signed with another secret
+    let foreign_signature = sign_payload(b"other_secret", Utc::now().timestamp(), &payload);
+    assert!(matches!(
+        verify_signature(WEBHOOK_SECRET.as_bytes(), &foreign_signature, &payload),
+        Err(PaymentError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn stale_signature_is_rejected() {
+    let payload = event_payload("evt_1", "pi_1");
+
+    let recent = sign_payload(
+        WEBHOOK_SECRET.as_bytes(),
+        Utc::now().timestamp() - 240,
+        &payload,
+    );
+    verify_signature(WEBHOOK_SECRET.as_bytes(), &recent, &payload).unwrap();
+
+    // Older than the 300 second tolerance, although correctly signed
+    let stale = sign_payload(
+        WEBHOOK_SECRET.as_bytes(),
+        Utc::now().timestamp() - 301,
+        &payload,
+    );
+    assert!(matches!(
+        verify_signature(WEBHOOK_SECRET.as_bytes(), &stale, &payload),
+        Err(PaymentError::InvalidSignature)
+    ));
+}
+
+/// The app on the test database, with the mock provider it signs webhooks with.
+async fn test_app() -> (Router, Arc<MockPaymentProvider>, DatabaseConnection) {
+    let database_url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+    let db = Database::connect(&database_url).await.unwrap();
+
+    let config = Arc::new(AppConfig {
+        database_url: Secret::from(database_url.as_str()),
+        cache_backend: CacheBackend::Memory,
+        jwt_secret: Some(Secret::from("jwt_secret_key")),
+        payment_webhook_secret: Secret::from(WEBHOOK_SECRET),
+        ..AppConfig::default()
+    });
+    let provider = Arc::new(MockPaymentProvider::new(WEBHOOK_SECRET));
+    let state = AppState {
+        db: db.clone(),
+        cache: Arc::new(MemoryCache::new()),
+        jwt_keys: JwtKeyStore::load(config.clone()).unwrap(),
+        queue: QueuePublisher::new(&config),
+        mailer: None,
+        payments: provider.clone(),
+        config,
+    };
+    (create_routes(state), provider, db)
+}
+
+/// A user with a pending order. Deleting the user deletes the order and its payments.
+async fn insert_pending_order(
+    db: &DatabaseConnection,
+    unique: &str,
+) -> (user::Model, order::Model) {
+    let now = Utc::now().naive_utc();
+    let user_model = user::ActiveModel {
+        name: Set("Webhook Test".to_owned()),
+        email: Set(format!("webhook_{}@example.com", unique)),
+        password: Set("not-a-hash".to_owned()),
+        role: Set("User".to_owned()),
+        token_version: Set(0),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let order_model = order::ActiveModel {
+        user_id: Set(user_model.id),
+        status: Set(order::STATUS_PENDING.to_owned()),
+        total: Set(1000),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    (user_model, order_model)
+}
+
+/// An authorized payment of the order.
+async fn insert_payment(
+    db: &DatabaseConnection,
+    provider: &MockPaymentProvider,
+    order_id: i32,
+    intent_id: &str,
+) {
+    let now = Utc::now().naive_utc();
+    payment::ActiveModel {
+        order_id: Set(order_id),
+        provider: Set(provider.name().to_owned()),
+        intent_id: Set(intent_id.to_owned()),
+        amount: Set(1000),
+        currency: Set("usd".to_owned()),
+        status: Set(payment::STATUS_REQUIRES_CAPTURE.to_owned()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// Delivers a signed webhook and returns the status and body of the response.
+async fn deliver(
+    router: &Router,
+    provider: &MockPaymentProvider,
+    payload: &[u8],
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/payments/webhook")
+        .header(SIGNATURE_HEADER, provider.sign_webhook(payload))
+        .header("Content-Type", "application/json")
+        .body(Body::from(payload.to_vec()))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+async fn recorded_events(db: &DatabaseConnection, event_id: &str) -> u64 {
+    PaymentEvent::find()
+        .filter(payment_event::Column::EventId.eq(event_id))
+        .count(db)
+        .await
+        .unwrap()
+}
+
+async fn order_status(db: &DatabaseConnection, order_id: i32) -> String {
+    Order::find_by_id(order_id)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+#[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+async fn redelivered_event_is_applied_once() {
+    let (router, provider, db) = test_app().await;
+
+    // A pending order with an authorized payment
+    let unique = Uuid::new_v4().simple().to_string();
+    let (user_model, order_model) = insert_pending_order(&db, &unique).await;
+    let intent_id = format!("pi_test_{}", unique);
+    insert_payment(&db, &provider, order_model.id, &intent_id).await;
+
+    let event_id = format!("evt_test_{}", unique);
+    let payload = event_payload(&event_id, &intent_id);
+    let mut messages = Vec::new();
+    for _ in 0..2 {
+        let (status, body) = deliver(&router, &provider, &payload).await;
+        assert_eq!(status, StatusCode::OK);
+        messages.push(body["message"].as_str().unwrap().to_owned());
+    }
+    assert_eq!(messages, ["Event processed.", "Event already processed."]);
+
+    assert_eq!(recorded_events(&db, &event_id).await, 1);
+    assert_eq!(order_status(&db, order_model.id).await, order::STATUS_PAID);
+    let paid_entries = OrderStatusHistory::find()
+        .filter(order_status_history::Column::OrderId.eq(order_model.id))
+        .filter(order_status_history::Column::ToStatus.eq(order::STATUS_PAID))
+        .count(&db)
+        .await
+        .unwrap();
+    assert_eq!(paid_entries, 1);
+
+    user::Entity::delete_by_id(user_model.id)
+        .exec(&db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+async fn event_for_an_unknown_intent_is_delivered_again() {
+    let (router, provider, db) = test_app().await;
+
+    let unique = Uuid::new_v4().simple().to_string();
+    let (user_model, order_model) = insert_pending_order(&db, &unique).await;
+    let intent_id = format!("pi_test_{}", unique);
+    let event_id = format!("evt_test_{}", unique);
+    let payload = event_payload(&event_id, &intent_id);
+
+    // The event arrives before the payment is stored: it is refused, not recorded
+    let (status, body) = deliver(&router, &provider, &payload).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", body);
+    assert_eq!(recorded_events(&db, &event_id).await, 0);
+    assert_eq!(
+        order_status(&db, order_model.id).await,
+        order::STATUS_PENDING
+    );
+
+    // So the provider's next delivery is applied
+    insert_payment(&db, &provider, order_model.id, &intent_id).await;
+    let (status, body) = deliver(&router, &provider, &payload).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["message"], "Event processed.");
+    assert_eq!(recorded_events(&db, &event_id).await, 1);
+    assert_eq!(order_status(&db, order_model.id).await, order::STATUS_PAID);
+
+    user::Entity::delete_by_id(user_model.id)
+        .exec(&db)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,136 @@
+//! Rate limited routes: requests over the limit get a 429 problem response that says when to
+//! try again, whatever the counting strategy.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use axum::middleware::from_fn_with_state;
+use axum::routing::get;
+use sea_orm::DatabaseConnection;
+use serde_json::Value;
+use tower::ServiceExt;
+
+use axum_seaorm_app::app::middleware::rate_limit_middleware::{RateLimit, rate_limit_middleware};
+use axum_seaorm_app::config::app_config::{AppConfig, CacheBackend, Secret};
+use axum_seaorm_app::config::app_state::AppState;
+use axum_seaorm_app::config::jwt_keys::JwtKeyStore;
+use axum_seaorm_app::config::memory_cache::MemoryCache;
+use axum_seaorm_app::config::payment_provider::MockPaymentProvider;
+use axum_seaorm_app::config::rabbitmq::QueuePublisher;
+
+fn state() -> AppState {
+    let config = Arc::new(AppConfig {
+        database_url: Secret::from("postgres://localhost/unused"),
+        cache_backend: CacheBackend::Memory,
+        jwt_secret: Some(Secret::from("jwt_secret_key")),
+        payment_webhook_secret: Secret::from("payment_webhook_secret"),
+        rabbitmq_pass: Secret::from("rabbitmq_pass"),
+        ..AppConfig::default()
+    });
+
+    AppState {
+        db: DatabaseConnection::Disconnected,
+        cache: Arc::new(MemoryCache::new()),
+        jwt_keys: JwtKeyStore::load(config.clone()).unwrap(),
+        queue: QueuePublisher::new(&config),
+        mailer: None,
+        payments: Arc::new(MockPaymentProvider::new("payment_webhook_secret")),
+        config,
+    }
+}
+
+/// A route limited to two requests a minute.
+fn limited_route(state: &AppState, rate_limit: RateLimit) -> Router {
+    Router::new().route(
+        "/limited",
+        get(|| async { "ok" }).layer(from_fn_with_state(
+            (state.clone(), rate_limit),
+            rate_limit_middleware,
+        )),
+    )
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_get_a_429_with_retry_after() {
+    let state = state();
+
+    for rate_limit in [
+        RateLimit::per_route("fixed", 2, 60),
+        RateLimit::per_route("sliding", 2, 60).sliding_window(),
+        RateLimit::per_route("bucket", 2, 60).token_bucket(),
+    ] {
+        let name = rate_limit.name;
+        let router = limited_route(&state, rate_limit);
+        let send = || {
+            router
+                .clone()
+                .oneshot(Request::get("/limited").body(Body::empty()).unwrap())
+        };
+
+        for remaining in ["1", "0"] {
+            let response = send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", name);
+            assert_eq!(response.headers()["X-RateLimit-Limit"], "2", "{}", name);
+            assert_eq!(
+                response.headers()["X-RateLimit-Remaining"],
+                remaining,
+                "{}",
+                name
+            );
+            assert!(!response.headers().contains_key("Retry-After"), "{}", name);
+        }
+
+        let response = send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS, "{}", name);
+        assert_eq!(response.headers()["X-RateLimit-Remaining"], "0", "{}", name);
+        let retry_after: u64 = response.headers()["Retry-After"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after), "{} {}", name, retry_after);
+
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["status"], 429, "{}", name);
+        assert_eq!(body["code"], "too_many_requests", "{}", name);
+        assert_eq!(
+            body["detail"],
+            format!(
+                "Too many requests. Please try again in {} seconds.",
+                retry_after
+            ),
+            "{}",
+            name
+        );
+    }
+}
+
+#[tokio::test]
+async fn each_client_has_its_own_limit() {
+    let state = state();
+    let router = limited_route(&state, RateLimit::per_ip("per_ip", 2, 60));
+    let send = |peer: &str| {
+        let mut request = Request::get("/limited").body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        router.clone().oneshot(request)
+    };
+
+    for _ in 0..2 {
+        let response = send("203.0.113.7:5000").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = send("203.0.113.7:5001").await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Another address still has its full allowance
+    let response = send("198.51.100.1:5000").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["X-RateLimit-Remaining"], "1");
+}
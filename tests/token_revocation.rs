@@ -0,0 +1,235 @@
+//! A logged-out token must be rejected everywhere: by every auth middleware, by the guest
+//! middlewares (which then treat the caller as a guest) and by the token refresh.
+//!
+//! The app runs on the in-memory cache without a database: a revoked token has to be
+//! rejected before anything is loaded from Postgres.
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode};
+use sea_orm::DatabaseConnection;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+use axum_seaorm_app::app::middleware::email_verified_middleware::email_verified_cache_key;
+use axum_seaorm_app::config::app_config::{AppConfig, CacheBackend, Secret};
+use axum_seaorm_app::config::app_state::AppState;
+use axum_seaorm_app::config::blacklist::{blacklist_token, revoked_jti_key};
+use axum_seaorm_app::config::cache::{Cache, SharedCache};
+use axum_seaorm_app::config::jwt::{TokenType, create_jwt, verify_jwt};
+use axum_seaorm_app::config::jwt_keys::JwtKeyStore;
+use axum_seaorm_app::config::memory_cache::MemoryCache;
+use axum_seaorm_app::config::payment_provider::MockPaymentProvider;
+use axum_seaorm_app::config::rabbitmq::QueuePublisher;
+use axum_seaorm_app::config::token_version::token_version_key;
+use axum_seaorm_app::routes::create_routes;
+
+const ADMIN_ID: i32 = 1;
+const CUSTOMER_ID: i32 = 2;
+
+/// A logged-in admin and customer, both with a verified email and token version 0.
+struct TestApp {
+    router: Router,
+    cache: SharedCache,
+    keys: JwtKeyStore,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let config = Arc::new(AppConfig {
+            database_url: Secret::from("postgres://localhost/unused"),
+            cache_backend: CacheBackend::Memory,
+            jwt_secret: Some(Secret::from("jwt_secret_key")),
+            payment_webhook_secret: Secret::from("payment_webhook_secret"),
+            rabbitmq_pass: Secret::from("rabbitmq_pass"),
+            ..AppConfig::default()
+        });
+        config.validate().unwrap();
+
+        let keys = JwtKeyStore::load(config.clone()).unwrap();
+        let cache: SharedCache = Arc::new(MemoryCache::new());
+
+        // Everything the middlewares would otherwise load from the database
+        for user_id in [ADMIN_ID, CUSTOMER_ID] {
+            cache
+                .set(&token_version_key(user_id), "0", 3600)
+                .await
+                .unwrap();
+            cache
+                .set(&email_verified_cache_key(&user_id.to_string()), "1", 3600)
+                .await
+                .unwrap();
+        }
+
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            cache: cache.clone(),
+            jwt_keys: keys.clone(),
+            queue: QueuePublisher::new(&config),
+            mailer: None,
+            payments: Arc::new(MockPaymentProvider::new("payment_webhook_secret")),
+            config,
+        };
+
+        Self {
+            router: create_routes(state),
+            cache,
+            keys,
+        }
+    }
+
+    fn token(&self, user_id: i32, role: &str, session_id: &str, token_type: TokenType) -> String {
+        create_jwt(
+            &self.keys,
+            &user_id.to_string(),
+            role,
+            session_id,
+            0,
+            false,
+            token_type,
+            3600,
+        )
+        .unwrap()
+    }
+
+    /// Revokes a token the way a logout does, by its jti. Returns the jti.
+    async fn log_out(&self, token: &str, token_type: TokenType) -> String {
+        let claims = verify_jwt(&self.keys, token, token_type).unwrap().claims;
+        blacklist_token(&self.cache, &claims.jti, claims.exp)
+            .await
+            .unwrap();
+        claims.jti
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: &str,
+        body: Value,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// Asserts that a request with the token is rejected as revoked.
+    async fn assert_revoked(&self, method: Method, uri: &str, token: &str) {
+        let (status, body) = self.request(method, uri, token, json!({})).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {}", uri, body);
+        assert_eq!(body["code"], "token_revoked", "{} {}", uri, body);
+    }
+
+    /// Asserts that every route tree rejects the admin and customer tokens.
+    async fn assert_rejected_everywhere(&self, admin_token: &str, customer_token: &str) {
+        // Admin account, auth and verified routes
+        self.assert_revoked(Method::POST, "/admin/2fa/setup", admin_token)
+            .await;
+        self.assert_revoked(Method::GET, "/admin/dashboard", admin_token)
+            .await;
+        self.assert_revoked(Method::GET, "/admin/orders", admin_token)
+            .await;
+
+        // Customer auth and verified routes
+        self.assert_revoked(Method::GET, "/customer/cart", customer_token)
+            .await;
+        self.assert_revoked(Method::POST, "/customer/checkout", customer_token)
+            .await;
+
+        // Guest routes let the caller through as a guest, up to the request validation
+        for (uri, token) in [
+            ("/admin/login", admin_token),
+            ("/customer/login", customer_token),
+        ] {
+            let (status, body) = self.request(Method::POST, uri, token, json!({})).await;
+            assert_ne!(status, StatusCode::FORBIDDEN, "{} {}", uri, body);
+        }
+    }
+}
+
+#[tokio::test]
+async fn active_tokens_are_accepted() {
+    let app = TestApp::new().await;
+    let admin_token = app.token(ADMIN_ID, "Admin", "admin-session", TokenType::Access);
+    let customer_token = app.token(CUSTOMER_ID, "User", "customer-session", TokenType::Access);
+
+    let (status, body) = app
+        .request(Method::GET, "/admin/dashboard", &admin_token, json!({}))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Logged-in users are kept out of the guest routes
+    for (uri, token) in [
+        ("/admin/login", &admin_token),
+        ("/customer/login", &customer_token),
+    ] {
+        let (status, body) = app.request(Method::POST, uri, token, json!({})).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} {}", uri, body);
+    }
+}
+
+#[tokio::test]
+async fn logged_out_tokens_are_rejected_on_every_route_tree() {
+    let app = TestApp::new().await;
+    let admin_token = app.token(ADMIN_ID, "Admin", "admin-session", TokenType::Access);
+    let customer_token = app.token(CUSTOMER_ID, "User", "customer-session", TokenType::Access);
+
+    for token in [&admin_token, &customer_token] {
+        let jti = app.log_out(token, TokenType::Access).await;
+
+        // The token is revoked by its jti, not by its raw value or its subject
+        assert!(app.cache.exists(&revoked_jti_key(&jti)).await.unwrap());
+        assert!(!app.cache.exists(token).await.unwrap());
+    }
+
+    app.assert_rejected_everywhere(&admin_token, &customer_token)
+        .await;
+
+    // Only the logged-out token is revoked, not the other tokens of its user
+    let other_token = app.token(ADMIN_ID, "Admin", "admin-session", TokenType::Access);
+    let (status, body) = app
+        .request(Method::GET, "/admin/dashboard", &other_token, json!({}))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn logged_out_refresh_token_cannot_be_refreshed() {
+    let app = TestApp::new().await;
+
+    for (uri, user_id, role) in [
+        ("/admin/refresh-token", ADMIN_ID, "Admin"),
+        ("/customer/refresh-token", CUSTOMER_ID, "User"),
+    ] {
+        let refresh_token = app.token(user_id, role, "other-session", TokenType::Refresh);
+        app.log_out(&refresh_token, TokenType::Refresh).await;
+
+        // The refresh token is the credential: an expired access token doesn't get in the way
+        let (status, body) = app
+            .request(
+                Method::POST,
+                uri,
+                "expired-access-token",
+                json!({ "refresh_token": refresh_token }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {}", uri, body);
+        assert_eq!(
+            body["detail"], "Refresh token has been revoked.",
+            "{} {}",
+            uri, body
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Every token carries its type, and each kind of token is only accepted where that kind is
+//! expected: a refresh, password reset or email verification token is no access token, and
+//! an access token opens none of the other doors.
+//!
+//! Like the revocation tests, the app runs on the in-memory cache without a database: a token
+//! of the wrong type has to be rejected before anything is loaded from Postgres.
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode};
+use sea_orm::DatabaseConnection;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+use axum_seaorm_app::config::app_config::{AppConfig, CacheBackend, Secret};
+use axum_seaorm_app::config::app_state::AppState;
+use axum_seaorm_app::config::jwt::{
+    TokenType, create_email_verification_jwt, create_jwt, create_mfa_pending_jwt,
+    create_password_reset_jwt, verify_email_verification_jwt, verify_jwt, verify_mfa_pending_jwt,
+    verify_password_reset_jwt,
+};
+use axum_seaorm_app::config::jwt_keys::JwtKeyStore;
+use axum_seaorm_app::config::memory_cache::MemoryCache;
+use axum_seaorm_app::config::payment_provider::MockPaymentProvider;
+use axum_seaorm_app::config::rabbitmq::QueuePublisher;
+use axum_seaorm_app::routes::create_routes;
+
+const USER_ID: &str = "1";
+
+fn config() -> Arc<AppConfig> {
+    let config = AppConfig {
+        database_url: Secret::from("postgres://localhost/unused"),
+        cache_backend: CacheBackend::Memory,
+        jwt_secret: Some(Secret::from("jwt_secret_key")),
+        payment_webhook_secret: Secret::from("payment_webhook_secret"),
+        rabbitmq_pass: Secret::from("rabbitmq_pass"),
+        ..AppConfig::default()
+    };
+    config.validate().unwrap();
+    Arc::new(config)
+}
+
+/// One valid token of every type, all for the same user.
+struct Tokens {
+    access: String,
+    refresh: String,
+    email_verification: String,
+    password_reset: String,
+    mfa_pending: String,
+}
+
+impl Tokens {
+    fn new(keys: &JwtKeyStore) -> Self {
+        let login_token = |token_type| {
+            create_jwt(
+                keys, USER_ID, "Admin", "session", 0, false, token_type, 3600,
+            )
+            .unwrap()
+        };
+        Self {
+            access: login_token(TokenType::Access),
+            refresh: login_token(TokenType::Refresh),
+            email_verification: create_email_verification_jwt(keys, USER_ID, 3600).unwrap(),
+            password_reset: create_password_reset_jwt(keys, USER_ID, 0, 3600).unwrap(),
+            mfa_pending: create_mfa_pending_jwt(keys, USER_ID, 0, 3600).unwrap(),
+        }
+    }
+
+    /// Every token except the one of the given type.
+    fn all_but(&self, token_type: TokenType) -> Vec<(TokenType, &str)> {
+        [
+            (TokenType::Access, self.access.as_str()),
+            (TokenType::Refresh, self.refresh.as_str()),
+            (
+                TokenType::EmailVerification,
+                self.email_verification.as_str(),
+            ),
+            (TokenType::PasswordReset, self.password_reset.as_str()),
+            (TokenType::MfaPending, self.mfa_pending.as_str()),
+        ]
+        .into_iter()
+        .filter(|(other, _)| *other != token_type)
+        .collect()
+    }
+}
+
+fn keys_and_tokens() -> (JwtKeyStore, Tokens) {
+    let keys = JwtKeyStore::load(config()).unwrap();
+    let tokens = Tokens::new(&keys);
+    (keys, tokens)
+}
+
+#[test]
+fn access_verification_rejects_other_token_types() {
+    let (keys, tokens) = keys_and_tokens();
+    verify_jwt(&keys, &tokens.access, TokenType::Access).unwrap();
+
+    for (token_type, token) in tokens.all_but(TokenType::Access) {
+        assert!(
+            verify_jwt(&keys, token, TokenType::Access).is_err(),
+            "{:?} token accepted as an access token",
+            token_type
+        );
+    }
+}
+
+#[test]
+fn refresh_verification_rejects_other_token_types() {
+    let (keys, tokens) = keys_and_tokens();
+    verify_jwt(&keys, &tokens.refresh, TokenType::Refresh).unwrap();
+
+    for (token_type, token) in tokens.all_but(TokenType::Refresh) {
+        assert!(
+            verify_jwt(&keys, token, TokenType::Refresh).is_err(),
+            "{:?} token accepted as a refresh token",
+            token_type
+        );
+    }
+}
+
+#[test]
+fn email_verification_rejects_other_token_types() {
+    let (keys, tokens) = keys_and_tokens();
+    verify_email_verification_jwt(&keys, &tokens.email_verification).unwrap();
+
+    for (token_type, token) in tokens.all_but(TokenType::EmailVerification) {
+        assert!(
+            verify_email_verification_jwt(&keys, token).is_err(),
+            "{:?} token accepted as an email verification token",
+            token_type
+        );
+    }
+}
+
+#[test]
+fn password_reset_verification_rejects_other_token_types() {
+    let (keys, tokens) = keys_and_tokens();
+    verify_password_reset_jwt(&keys, &tokens.password_reset).unwrap();
+
+    for (token_type, token) in tokens.all_but(TokenType::PasswordReset) {
+        assert!(
+            verify_password_reset_jwt(&keys, token).is_err(),
+            "{:?} token accepted as a password reset token",
+            token_type
+        );
+    }
+}
+
+#[test]
+fn mfa_pending_verification_rejects_other_token_types() {
+    let (keys, tokens) = keys_and_tokens();
+    verify_mfa_pending_jwt(&keys, &tokens.mfa_pending).unwrap();
+
+    for (token_type, token) in tokens.all_but(TokenType::MfaPending) {
+        assert!(
+            verify_mfa_pending_jwt(&keys, token).is_err(),
+            "{:?} token accepted as an mfa pending token",
+            token_type
+        );
+    }
+}
+
+async fn request(
+    router: &Router,
+    method: Method,
+    uri: &str,
+    bearer: Option<&str>,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json");
+    if let Some(token) = bearer {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, body)
+}
+
+#[tokio::test]
+async fn routes_only_accept_their_own_token_type() {
+    let config = config();
+    let keys = JwtKeyStore::load(config.clone()).unwrap();
+    let tokens = Tokens::new(&keys);
+    let state = AppState {
+        db: DatabaseConnection::Disconnected,
+        cache: Arc::new(MemoryCache::new()),
+        jwt_keys: keys,
+        queue: QueuePublisher::new(&config),
+        mailer: None,
+        payments: Arc::new(MockPaymentProvider::new("payment_webhook_secret")),
+        config,
+    };
+    let router = create_routes(state);
+
+    // Only an access token opens the authenticated routes
+    for (token_type, token) in tokens.all_but(TokenType::Access) {
+        let (status, body) = request(
+            &router,
+            Method::GET,
+            "/admin/dashboard",
+            Some(token),
+            json!({}),
+        )
+        .await;
+        assert_eq!(
+            status,
+            StatusCode::UNAUTHORIZED,
+            "{:?} {}",
+            token_type,
+            body
+        );
+    }
+
+    // Only a refresh token is refreshed
+    for (token_type, token) in tokens.all_but(TokenType::Refresh) {
+        let (status, body) = request(
+            &router,
+            Method::POST,
+            "/admin/refresh-token",
+            None,
+            json!({ "refresh_token": token }),
+        )
+        .await;
+        assert_eq!(
+            status,
+            StatusCode::UNAUTHORIZED,
+            "{:?} {}",
+            token_type,
+            body
+        );
+        assert_eq!(body["detail"], "Invalid or expired refresh token.");
+    }
+
+    // Only a password reset token resets the password
+    for (token_type, token) in tokens.all_but(TokenType::PasswordReset) {
+        let (status, body) = request(
+            &router,
+            Method::POST,
+            "/customer/reset-password",
+            None,
+            json!({ "token": token, "password": "N3w-Passw0rd!" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?} {}", token_type, body);
+        assert_eq!(body["detail"], "Invalid or expired password reset token.");
+    }
+
+    // Only an email verification token verifies the email
+    for (token_type, token) in tokens.all_but(TokenType::EmailVerification) {
+        let uri = format!("/customer/verify-email/{}", token);
+        let (status, body) = request(&router, Method::GET, &uri, None, json!({})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?} {}", token_type, body);
+        assert_eq!(body["detail"], "Invalid or expired verification token.");
+    }
+}